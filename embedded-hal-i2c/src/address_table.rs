@@ -0,0 +1,107 @@
+//! Allocation-free mapping from addresses to handlers
+
+use crate::AnyAddress;
+use heapless::Vec;
+
+/// Error returned by [`AddressTable::insert`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressTableError {
+    /// The address is reserved by the I2C specification, and cannot be used
+    /// by a regular device.
+    Reserved,
+    /// The address does not fit in its address mode: more than 7 or 10 bits.
+    OutOfRange,
+    /// The table already holds `N` entries.
+    Full,
+}
+
+/// Table of up to `N` entries of type `T`, looked up by address
+///
+/// 7-bit and 10-bit addresses are distinct, so `AnyAddress::Seven(0x50)` and
+/// `AnyAddress::Ten(0x50)` can map to different entries. The 7-bit addresses
+/// reserved by the I2C specification, `0x00..=0x07` and `0x78..=0x7F`, are
+/// rejected.
+pub struct AddressTable<T, const N: usize> {
+    entries: Vec<(AnyAddress, T), N>,
+}
+
+impl<T, const N: usize> AddressTable<T, N> {
+    /// Create an empty table.
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Map `address` to `value`, returning the value it replaced, if any.
+    pub fn insert(
+        &mut self,
+        address: AnyAddress,
+        value: T,
+    ) -> Result<Option<T>, AddressTableError> {
+        match address {
+            AnyAddress::Seven(address) if address > 0x7F => {
+                return Err(AddressTableError::OutOfRange);
+            }
+            AnyAddress::Seven(0x00..=0x07 | 0x78..=0x7F) => {
+                return Err(AddressTableError::Reserved);
+            }
+            AnyAddress::Ten(address) if address > 0x3FF => {
+                return Err(AddressTableError::OutOfRange);
+            }
+            _ => {}
+        }
+
+        if let Some(entry) = self.get_mut(address) {
+            return Ok(Some(core::mem::replace(entry, value)));
+        }
+        self.entries
+            .push((address, value))
+            .map_err(|_| AddressTableError::Full)?;
+        Ok(None)
+    }
+
+    /// The entry for `address`, if any.
+    pub fn get(&self, address: AnyAddress) -> Option<&T> {
+        self.entries
+            .iter()
+            .find(|(a, _)| *a == address)
+            .map(|(_, value)| value)
+    }
+
+    /// The entry for `address`, if any, for modification.
+    pub fn get_mut(&mut self, address: AnyAddress) -> Option<&mut T> {
+        self.entries
+            .iter_mut()
+            .find(|(a, _)| *a == address)
+            .map(|(_, value)| value)
+    }
+
+    /// Remove the entry for `address`, returning it if there was one.
+    pub fn remove(&mut self, address: AnyAddress) -> Option<T> {
+        let index = self.entries.iter().position(|(a, _)| *a == address)?;
+        Some(self.entries.swap_remove(index).1)
+    }
+
+    /// All addresses in the table, in no particular order.
+    pub fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        self.entries.iter().map(|(address, _)| *address)
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the table has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<T, const N: usize> Default for AddressTable<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,70 @@
+//! Blocking adapter for asynchronous controllers
+
+use crate::{AddressMode, AsyncI2cController, ErrorType, Operation, SyncI2cController};
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+use std::sync::Arc;
+use std::task::Wake;
+use std::thread::{self, Thread};
+
+/// Wraps an [`AsyncI2cController`] to implement [`SyncI2cController`]
+///
+/// Every transaction is run to completion on the calling thread, parking the
+/// thread whenever the inner future is waiting.
+///
+/// # Reentrancy
+///
+/// Blocking the calling thread means no other work can happen on it while the
+/// transaction is in progress. Using this from within an async executor will
+/// deadlock if the inner controller depends on tasks running on that same
+/// executor thread, such as a simulated target joined on the same task.
+pub struct BlockOnController<C> {
+    inner: C,
+}
+
+impl<C> BlockOnController<C> {
+    /// Create a blocking controller from an async one.
+    pub const fn new(inner: C) -> Self {
+        Self { inner }
+    }
+
+    /// Release the wrapped controller.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C: ErrorType> ErrorType for BlockOnController<C> {
+    type Error = C::Error;
+}
+
+impl<A: AddressMode, C: AsyncI2cController<A>> SyncI2cController<A> for BlockOnController<C> {
+    fn transaction(
+        &mut self,
+        address: A,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        block_on(self.inner.transaction(address, operations))
+    }
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
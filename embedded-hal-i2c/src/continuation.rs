@@ -0,0 +1,68 @@
+//! Fluent chaining of reads
+
+use crate::{AsyncReadTransaction, ReadResult};
+
+/// Adapter over an [`AsyncReadTransaction`] for providing the data of a read
+/// in multiple pieces.
+///
+/// Once the master ends the read, further pieces are ignored. The total number
+/// of bytes read by the master is returned by [`ReadContinuation::finish`].
+///
+/// ```
+/// use embedded_hal_i2c::AsyncReadTransaction;
+/// use embedded_hal_i2c::continuation::ReadContinuation;
+///
+/// async fn respond<R: AsyncReadTransaction>(handler: R, header: u8, payload: &[u8]) -> Result<usize, R::Error> {
+///     let checksum = payload.iter().fold(header, |acc, b| acc.wrapping_add(*b));
+///     ReadContinuation::new(handler)
+///         .then(&[header])
+///         .await?
+///         .then(payload)
+///         .await?
+///         .then(&[checksum])
+///         .await?
+///         .finish(0xFF)
+///         .await
+/// }
+/// ```
+pub struct ReadContinuation<R> {
+    handler: Option<R>,
+    total: usize,
+}
+
+impl<R: AsyncReadTransaction> ReadContinuation<R> {
+    /// Start a read on `handler`.
+    pub const fn new(handler: R) -> Self {
+        Self {
+            handler: Some(handler),
+            total: 0,
+        }
+    }
+
+    /// Provide the next piece of data to the master.
+    pub async fn then(self, buffer: &[u8]) -> Result<Self, R::Error> {
+        let Some(handler) = self.handler else {
+            return Ok(self);
+        };
+
+        Ok(match handler.handle_part(buffer).await? {
+            ReadResult::Partial(handler) => Self {
+                handler: Some(handler),
+                total: self.total + buffer.len(),
+            },
+            ReadResult::Complete(size) => Self {
+                handler: None,
+                total: self.total + size,
+            },
+        })
+    }
+
+    /// Complete the read, providing the overrun character `ovc` for any
+    /// further bytes the master reads. Returns the total number of bytes read.
+    pub async fn finish(self, ovc: u8) -> Result<usize, R::Error> {
+        match self.handler {
+            Some(handler) => Ok(self.total + handler.handle_complete(&[], ovc).await?),
+            None => Ok(self.total),
+        }
+    }
+}
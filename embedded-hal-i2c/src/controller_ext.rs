@@ -0,0 +1,349 @@
+//! Convenience functions for controllers
+
+use crate::{
+    AddressMode, AsyncI2cController, Error, ErrorKind, ErrorType, Operation, SevenBitAddress,
+    TenBitAddress,
+};
+use embedded_hal_async::delay::DelayNs;
+
+/// The reserved address of the general call.
+pub const GENERAL_CALL_ADDRESS: SevenBitAddress = 0x00;
+
+/// The reserved address used to read the device ID of a target.
+pub const DEVICE_ID_ADDRESS: SevenBitAddress = 0x7C;
+
+/// Interval between the polls of [`AsyncI2cControllerExt::wait_ready`], in
+/// microseconds.
+pub const ACK_POLL_INTERVAL_US: u32 = 100;
+
+/// Error returned by [`AsyncI2cControllerExt::write_verify`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VerifyError<E> {
+    /// Error of the controller
+    I2c(E),
+    /// The data read back differs from the data written
+    Mismatch(VerifyMismatch),
+}
+
+/// The first difference between the data written and read back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct VerifyMismatch {
+    /// Offset of the first differing byte in the data
+    pub offset: usize,
+}
+
+/// Error returned by [`AsyncI2cControllerExt::write_pages`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PageWriteError<E> {
+    /// Error of the controller
+    pub error: E,
+    /// Number of bytes of the data written successfully before the error, to
+    /// resume from
+    pub written: usize,
+}
+
+/// Identification of a target, as read with
+/// [`AsyncI2cControllerExt::read_device_id`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceId {
+    /// Manufacturer, 12 bits
+    pub manufacturer: u16,
+    /// Part identification assigned by the manufacturer, 9 bits
+    pub part: u16,
+    /// Die revision, 3 bits
+    pub revision: u8,
+}
+
+impl DeviceId {
+    /// Decode the three bytes of a device ID read.
+    pub const fn from_bytes(bytes: [u8; 3]) -> Self {
+        let [high, mid, low] = bytes;
+        Self {
+            manufacturer: (high as u16) << 4 | (mid >> 4) as u16,
+            part: ((mid & 0x0F) as u16) << 5 | (low >> 3) as u16,
+            revision: low & 0x07,
+        }
+    }
+
+    /// Encode the device ID as the three bytes of a device ID read. Bits
+    /// beyond the width of each field are ignored.
+    pub const fn to_bytes(self) -> [u8; 3] {
+        [
+            (self.manufacturer >> 4) as u8,
+            (self.manufacturer << 4) as u8 | (self.part >> 5) as u8 & 0x0F,
+            (self.part << 3) as u8 | self.revision & 0x07,
+        ]
+    }
+}
+
+impl<E> From<E> for VerifyError<E> {
+    fn from(value: E) -> Self {
+        Self::I2c(value)
+    }
+}
+
+/// Extension trait with common operations built on
+/// [`AsyncI2cController::transaction`]
+///
+/// This is implemented for every [`AsyncI2cController`].
+pub trait AsyncI2cControllerExt: AsyncI2cController<SevenBitAddress> {
+    /// Write `data` to all targets on the bus at once, using the general call
+    /// address.
+    async fn general_call(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.write(GENERAL_CALL_ADDRESS, data).await
+    }
+
+    /// Wait until the target acknowledges its address, e.g. after starting the
+    /// internal write cycle of an EEPROM.
+    ///
+    /// Issues empty writes to `address` every [`ACK_POLL_INTERVAL_US`], until
+    /// one is acknowledged or `timeout_us` microseconds of delay have passed.
+    /// The time taken by the writes themselves is not counted. On timeout the
+    /// error of the last write is returned. Errors other than a missing
+    /// acknowledgement are returned right away.
+    async fn wait_ready(
+        &mut self,
+        address: SevenBitAddress,
+        delay: &mut impl DelayNs,
+        timeout_us: u32,
+    ) -> Result<(), Self::Error> {
+        let mut waited = 0;
+        loop {
+            match self.write(address, &[]).await {
+                Err(e)
+                    if matches!(e.kind(), ErrorKind::NoAcknowledge(_)) && waited < timeout_us =>
+                {
+                    delay.delay_us(ACK_POLL_INTERVAL_US).await;
+                    waited = waited.saturating_add(ACK_POLL_INTERVAL_US);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Write `data` to the register at `register`, then read it back to check
+    /// that it arrived intact.
+    ///
+    /// `register` holds the register address bytes, so any register address
+    /// width can be used. The write is assembled in `buffer`, and the data is
+    /// then read back into it in a single read, relying on the target
+    /// incrementing its register address after every byte.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is shorter than `register` and `data` combined.
+    async fn write_verify(
+        &mut self,
+        address: SevenBitAddress,
+        register: &[u8],
+        data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), VerifyError<Self::Error>> {
+        let message = &mut buffer[..register.len() + data.len()];
+        message[..register.len()].copy_from_slice(register);
+        message[register.len()..].copy_from_slice(data);
+        self.write(address, message).await?;
+
+        let buffer = &mut buffer[..data.len()];
+        self.write_read(address, register, buffer).await?;
+        match data.iter().zip(buffer.iter()).position(|(a, b)| a != b) {
+            Some(offset) => Err(VerifyError::Mismatch(VerifyMismatch { offset })),
+            None => Ok(()),
+        }
+    }
+
+    /// Write `data` to the memory of the target, starting at memory address
+    /// `start`, one page of `page_size` bytes per write.
+    ///
+    /// Every write holds the two byte memory address, most significant byte
+    /// first as used by most EEPROMs, followed by the data. Writes never cross
+    /// a page boundary, so the first write is shorter if `start` is not page
+    /// aligned. Each write is assembled in `buffer`, and `progress` is called
+    /// with the number of bytes of `data` written so far after each of them.
+    ///
+    /// Should a write fail, the number of bytes written before it is returned
+    /// along with the error, so the caller can resume by writing the rest of
+    /// `data` starting at `start` plus that number.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `page_size` is 0, or `buffer` is shorter than `page_size`
+    /// plus the two memory address bytes.
+    async fn write_pages(
+        &mut self,
+        address: SevenBitAddress,
+        start: u16,
+        data: &[u8],
+        page_size: usize,
+        buffer: &mut [u8],
+        mut progress: impl FnMut(usize),
+    ) -> Result<(), PageWriteError<Self::Error>> {
+        assert!(page_size > 0, "page size must not be 0");
+        let mut written = 0;
+        while written < data.len() {
+            let memory_address = usize::from(start) + written;
+            let len = (page_size - memory_address % page_size).min(data.len() - written);
+            let message = &mut buffer[..2 + len];
+            message[..2].copy_from_slice(&(memory_address as u16).to_be_bytes());
+            message[2..].copy_from_slice(&data[written..written + len]);
+            self.write(address, message)
+                .await
+                .map_err(|error| PageWriteError { error, written })?;
+            written += len;
+            progress(written);
+        }
+        Ok(())
+    }
+
+    /// Read `count` consecutive registers starting at `start` into `buffer`,
+    /// returning the part of `buffer` that was filled.
+    ///
+    /// Writes the one-byte register address `start`, then reads `count` bytes
+    /// after a repeated start. This relies on the target incrementing its
+    /// register address after every byte read.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is larger than `buffer`.
+    async fn dump_registers<'b>(
+        &mut self,
+        address: SevenBitAddress,
+        start: u8,
+        count: usize,
+        buffer: &'b mut [u8],
+    ) -> Result<&'b [u8], Self::Error> {
+        let buffer = &mut buffer[..count];
+        self.write_read(address, &[start], buffer).await?;
+        Ok(buffer)
+    }
+
+    /// Perform an SMBus send byte: write the single byte `byte`, usually a
+    /// command without parameters.
+    async fn send_byte(&mut self, address: SevenBitAddress, byte: u8) -> Result<(), Self::Error> {
+        self.write(address, &[byte]).await
+    }
+
+    /// Perform an SMBus receive byte: read a single byte from the target.
+    async fn receive_byte(&mut self, address: SevenBitAddress) -> Result<u8, Self::Error> {
+        let mut byte = [0];
+        self.read(address, &mut byte).await?;
+        Ok(byte[0])
+    }
+
+    /// Perform an SMBus process call: write `send` to the command `command`,
+    /// then read the word the target answers with after a repeated start.
+    ///
+    /// Both words are transferred least significant byte first, as SMBus
+    /// requires.
+    async fn process_call(
+        &mut self,
+        address: SevenBitAddress,
+        command: u8,
+        send: u16,
+    ) -> Result<u16, Self::Error> {
+        let [low, high] = send.to_le_bytes();
+        let mut response = [0; 2];
+        self.write_read(address, &[command, low, high], &mut response)
+            .await?;
+        Ok(u16::from_le_bytes(response))
+    }
+
+    /// Read the device ID of the target at `address`.
+    ///
+    /// Writes the address of the target to [`DEVICE_ID_ADDRESS`], then reads
+    /// the three ID bytes from it after a repeated start, as specified by the
+    /// I2C specification. Only targets supporting the device ID command
+    /// acknowledge this.
+    async fn read_device_id(&mut self, address: SevenBitAddress) -> Result<DeviceId, Self::Error> {
+        let mut bytes = [0; 3];
+        self.write_read(DEVICE_ID_ADDRESS, &[address << 1], &mut bytes)
+            .await?;
+        Ok(DeviceId::from_bytes(bytes))
+    }
+
+    /// Write `data` to the target with 10-bit address `address`.
+    ///
+    /// Shorthand for [`AsyncI2cController::write`] with a [`TenBitAddress`],
+    /// as plain integer literals are taken to be 7-bit addresses. Only the
+    /// lower 10 bits of `address` may be set.
+    async fn write_ten_bit(
+        &mut self,
+        address: TenBitAddress,
+        data: &[u8],
+    ) -> Result<(), Self::Error>
+    where
+        Self: AsyncI2cController<TenBitAddress>,
+    {
+        AsyncI2cController::<TenBitAddress>::write(self, address, data).await
+    }
+
+    /// Read enough bytes from the target with 10-bit address `address` to
+    /// fill `buffer`.
+    async fn read_ten_bit(
+        &mut self,
+        address: TenBitAddress,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error>
+    where
+        Self: AsyncI2cController<TenBitAddress>,
+    {
+        AsyncI2cController::<TenBitAddress>::read(self, address, buffer).await
+    }
+
+    /// Write `data` to the target with 10-bit address `address`, then read
+    /// enough bytes to fill `buffer` after a repeated start.
+    async fn write_read_ten_bit(
+        &mut self,
+        address: TenBitAddress,
+        data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error>
+    where
+        Self: AsyncI2cController<TenBitAddress>,
+    {
+        AsyncI2cController::<TenBitAddress>::write_read(self, address, data, buffer).await
+    }
+}
+
+impl<T: AsyncI2cController<SevenBitAddress> + ?Sized> AsyncI2cControllerExt for T {}
+
+/// Controller able to address a different target after a repeated start,
+/// without releasing the bus in between.
+///
+/// [`AsyncI2cController::transaction`] uses a single address for all
+/// operations. Bus bridges and multiplexers may need to address multiple
+/// devices in one held-bus sequence instead.
+pub trait AsyncI2cMultiAddressController<A: AddressMode = SevenBitAddress>: ErrorType {
+    /// Execute the provided operations, each on its own address.
+    ///
+    /// Transaction contract:
+    /// - Before executing the first operation a start condition is sent,
+    ///   followed by the address of that operation.
+    /// - Before every following operation a repeated start is sent, followed
+    ///   by the address of that operation.
+    /// - After executing the last operation a stop condition is sent.
+    async fn transaction_multi(
+        &mut self,
+        operations: &mut [(A, Operation<'_>)],
+    ) -> Result<(), Self::Error>;
+
+    /// Write `a_bytes` to `a_address`, then after a repeated start write
+    /// `b_bytes` to `b_address`.
+    async fn write_restart_write(
+        &mut self,
+        a_address: A,
+        a_bytes: &[u8],
+        b_address: A,
+        b_bytes: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.transaction_multi(&mut [
+            (a_address, Operation::Write(a_bytes)),
+            (b_address, Operation::Write(b_bytes)),
+        ])
+        .await
+    }
+}
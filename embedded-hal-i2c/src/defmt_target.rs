@@ -0,0 +1,128 @@
+//! Target wrapper logging all activity over defmt
+
+use crate::{
+    AnyAddress, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction, ReadResult,
+    Transaction, WriteResult,
+};
+
+/// Wraps an [`AsyncI2cTarget`] to log every transaction and handler call with
+/// defmt.
+///
+/// Regular activity is logged at the trace level. Errors of the wrapped target
+/// are logged at the error level, without their contents as these need not
+/// implement `defmt::Format`.
+pub struct DefmtTarget<T> {
+    inner: T,
+}
+
+impl<T> DefmtTarget<T> {
+    /// Log all activity on `inner`.
+    pub const fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Release the wrapped target.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsyncI2cTarget> AsyncI2cTarget for DefmtTarget<T> {
+    type Error = T::Error;
+    type Read<'a>
+        = DefmtRead<T::Read<'a>>
+    where
+        Self: 'a;
+    type Write<'a>
+        = DefmtWrite<T::Write<'a>>
+    where
+        Self: 'a;
+
+    async fn listen(
+        &mut self,
+    ) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        let transaction = self.inner.listen().await.inspect_err(|_| {
+            defmt::error!("i2c target: listen failed");
+        })?;
+        defmt::trace!("i2c target: {}", transaction.tag());
+        Ok(match transaction {
+            Transaction::Deselect => Transaction::Deselect,
+            Transaction::Read { address, handler } => Transaction::Read {
+                address,
+                handler: DefmtRead { inner: handler },
+            },
+            Transaction::Write { address, handler } => Transaction::Write {
+                address,
+                handler: DefmtWrite { inner: handler },
+            },
+        })
+    }
+
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        self.inner.addresses()
+    }
+
+    fn ops_remaining(&self) -> Option<usize> {
+        self.inner.ops_remaining()
+    }
+
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        self.inner.clock_range()
+    }
+
+    fn take_reset(&mut self) -> bool {
+        self.inner.take_reset()
+    }
+}
+
+/// Read handler for [`DefmtTarget`]
+pub struct DefmtRead<R> {
+    inner: R,
+}
+
+impl<R: AsyncReadTransaction> AsyncReadTransaction for DefmtRead<R> {
+    type Error = R::Error;
+
+    const MAX_OVERRUN: usize = R::MAX_OVERRUN;
+
+    async fn handle_part(self, buffer: &[u8]) -> Result<ReadResult<Self>, Self::Error> {
+        let result = self.inner.handle_part(buffer).await.inspect_err(|_| {
+            defmt::error!("i2c target: read failed");
+        })?;
+        Ok(match result {
+            ReadResult::Complete(size) => {
+                defmt::trace!("i2c target: read {=[u8]}, complete", buffer[..size]);
+                ReadResult::Complete(size)
+            }
+            ReadResult::Partial(inner) => {
+                defmt::trace!("i2c target: read {=[u8]}", buffer);
+                ReadResult::Partial(Self { inner })
+            }
+        })
+    }
+}
+
+/// Write handler for [`DefmtTarget`]
+pub struct DefmtWrite<W> {
+    inner: W,
+}
+
+impl<W: AsyncWriteTransaction> AsyncWriteTransaction for DefmtWrite<W> {
+    type Error = W::Error;
+
+    async fn handle_part(self, buffer: &mut [u8]) -> Result<WriteResult<Self>, Self::Error> {
+        let result = self.inner.handle_part(buffer).await.inspect_err(|_| {
+            defmt::error!("i2c target: write failed");
+        })?;
+        Ok(match result {
+            WriteResult::Complete(size) => {
+                defmt::trace!("i2c target: write {=[u8]}, complete", buffer[..size]);
+                WriteResult::Complete(size)
+            }
+            WriteResult::Partial(inner) => {
+                defmt::trace!("i2c target: write {=[u8]}", buffer);
+                WriteResult::Partial(Self { inner })
+            }
+        })
+    }
+}
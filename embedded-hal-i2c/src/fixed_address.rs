@@ -0,0 +1,78 @@
+//! Target wrapper responding to a single configured address
+
+use crate::{AnyAddress, AsyncI2cTarget, Transaction};
+
+/// Wraps an [`AsyncI2cTarget`] such that only transactions for one address
+/// reach the application, and [`AsyncI2cTarget::addresses`] reports it.
+///
+/// This allows target code to configure its own address, also on hardware or
+/// simulators that answer more addresses. Transactions for other addresses are
+/// not acknowledged, so the controller gets
+/// [`ErrorKind::NoAcknowledge`](crate::ErrorKind::NoAcknowledge) for the
+/// address, and `listen` reports them as [`Transaction::Deselect`].
+pub struct FixedAddressTarget<T> {
+    inner: T,
+    address: AnyAddress,
+}
+
+impl<T> FixedAddressTarget<T> {
+    /// Only pass the transactions of `inner` for `address` on.
+    pub const fn new(inner: T, address: AnyAddress) -> Self {
+        Self { inner, address }
+    }
+
+    /// The address the target responds to.
+    pub const fn address(&self) -> AnyAddress {
+        self.address
+    }
+
+    /// Release the wrapped target.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsyncI2cTarget> AsyncI2cTarget for FixedAddressTarget<T> {
+    type Error = T::Error;
+    type Read<'a>
+        = T::Read<'a>
+    where
+        Self: 'a;
+    type Write<'a>
+        = T::Write<'a>
+    where
+        Self: 'a;
+
+    async fn listen(
+        &mut self,
+    ) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        Ok(match self.inner.listen().await? {
+            // Dropping the handler does not acknowledge the address
+            Transaction::Read { address, handler } if address != self.address => {
+                drop(handler);
+                Transaction::Deselect
+            }
+            Transaction::Write { address, handler } if address != self.address => {
+                drop(handler);
+                Transaction::Deselect
+            }
+            transaction => transaction,
+        })
+    }
+
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        core::iter::once(self.address)
+    }
+
+    fn ops_remaining(&self) -> Option<usize> {
+        self.inner.ops_remaining()
+    }
+
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        self.inner.clock_range()
+    }
+
+    fn take_reset(&mut self) -> bool {
+        self.inner.take_reset()
+    }
+}
@@ -0,0 +1,148 @@
+//! Target wrapper capping the number of bytes in a single transaction
+
+use crate::{
+    AnyAddress, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction, ReadResult,
+    Transaction, WriteResult,
+};
+use core::cmp::min;
+
+/// Wraps an [`AsyncI2cTarget`] such that no read or write transaction can
+/// exceed `max_len` bytes.
+///
+/// Writes longer than the limit are truncated: the first `max_len` bytes are
+/// acknowledged as usual, the byte after that is not acknowledged, ending the
+/// transaction. Reads longer than the limit only get `max_len` bytes of data,
+/// after which the handler is dropped and the rest of the read is filled with
+/// the implementation-defined overrun character.
+pub struct LengthLimitedTarget<T> {
+    inner: T,
+    max_len: usize,
+}
+
+impl<T> LengthLimitedTarget<T> {
+    /// Limit every transaction on `inner` to at most `max_len` bytes.
+    pub const fn new(inner: T, max_len: usize) -> Self {
+        Self { inner, max_len }
+    }
+
+    /// The maximum number of bytes allowed in a single transaction.
+    pub const fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Release the wrapped target.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsyncI2cTarget> AsyncI2cTarget for LengthLimitedTarget<T> {
+    type Error = T::Error;
+    type Read<'a>
+        = LengthLimitedRead<T::Read<'a>>
+    where
+        Self: 'a;
+    type Write<'a>
+        = LengthLimitedWrite<T::Write<'a>>
+    where
+        Self: 'a;
+
+    async fn listen(
+        &mut self,
+    ) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        let max_len = self.max_len;
+        Ok(self.inner.listen().await?.map_handlers(
+            |inner| LengthLimitedRead {
+                inner,
+                remaining: max_len,
+            },
+            |inner| LengthLimitedWrite {
+                inner,
+                remaining: max_len,
+            },
+        ))
+    }
+
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        self.inner.addresses()
+    }
+
+    fn ops_remaining(&self) -> Option<usize> {
+        self.inner.ops_remaining()
+    }
+
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        self.inner.clock_range()
+    }
+
+    fn take_reset(&mut self) -> bool {
+        self.inner.take_reset()
+    }
+}
+
+/// Read handler for [`LengthLimitedTarget`]
+pub struct LengthLimitedRead<R> {
+    inner: R,
+    remaining: usize,
+}
+
+impl<R: AsyncReadTransaction> AsyncReadTransaction for LengthLimitedRead<R> {
+    type Error = R::Error;
+
+    const MAX_OVERRUN: usize = R::MAX_OVERRUN;
+
+    async fn handle_part(self, buffer: &[u8]) -> Result<ReadResult<Self>, Self::Error> {
+        if !buffer.is_empty() && self.remaining == 0 {
+            // Limit reached, let the inner handler provide the overrun character.
+            drop(self.inner);
+            return Ok(ReadResult::Complete(0));
+        }
+
+        let len = min(buffer.len(), self.remaining);
+        match self.inner.handle_part(&buffer[..len]).await? {
+            ReadResult::Complete(size) => Ok(ReadResult::Complete(size)),
+            ReadResult::Partial(inner) if len < buffer.len() => {
+                drop(inner);
+                Ok(ReadResult::Complete(len))
+            }
+            ReadResult::Partial(inner) => Ok(ReadResult::Partial(Self {
+                inner,
+                remaining: self.remaining - len,
+            })),
+        }
+    }
+}
+
+/// Write handler for [`LengthLimitedTarget`]
+pub struct LengthLimitedWrite<W> {
+    inner: W,
+    remaining: usize,
+}
+
+impl<W: AsyncWriteTransaction> AsyncWriteTransaction for LengthLimitedWrite<W> {
+    type Error = W::Error;
+
+    async fn handle_part(self, buffer: &mut [u8]) -> Result<WriteResult<Self>, Self::Error> {
+        if buffer.len() <= self.remaining {
+            return Ok(match self.inner.handle_part(buffer).await? {
+                WriteResult::Complete(size) => WriteResult::Complete(size),
+                WriteResult::Partial(inner) => WriteResult::Partial(Self {
+                    inner,
+                    remaining: self.remaining - buffer.len(),
+                }),
+            });
+        }
+
+        // The inner handler does not acknowledge the first byte over the limit.
+        let size = self
+            .inner
+            .handle_complete(&mut buffer[..self.remaining])
+            .await?;
+        Ok(WriteResult::Complete(size))
+    }
+
+    async fn handle_complete(self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        let len = min(buffer.len(), self.remaining);
+        self.inner.handle_complete(&mut buffer[..len]).await
+    }
+}
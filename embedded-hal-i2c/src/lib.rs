@@ -1,6 +1,9 @@
 #![no_std]
 #![allow(async_fn_in_trait)]
 
+#[cfg(feature = "std")]
+extern crate std;
+
 pub use embedded_hal::i2c::I2c as SyncI2cController;
 pub use embedded_hal::i2c::{
     AddressMode, Error, ErrorKind, ErrorType, NoAcknowledgeSource, Operation, SevenBitAddress,
@@ -8,13 +11,155 @@ pub use embedded_hal::i2c::{
 };
 pub use embedded_hal_async::i2c::I2c as AsyncI2cController;
 
+use core::time::Duration;
+
+#[cfg(feature = "heapless")]
+pub mod address_table;
+#[cfg(feature = "std")]
+pub mod block_on;
+pub mod continuation;
+pub mod controller_ext;
+#[cfg(feature = "defmt")]
+pub mod defmt_target;
+pub mod fixed_address;
+pub mod length_limited;
+pub mod paced;
+#[cfg(feature = "std")]
+pub mod progress;
+pub mod register;
+pub mod retry;
+pub mod seven_bit;
+pub mod smbus;
+#[cfg(feature = "bytemuck")]
+pub mod struct_target;
+pub mod target_ext;
+#[cfg(feature = "heapless")]
+pub mod transaction_log;
+
+/// Build an array of [`Operation`]s for
+/// [`AsyncI2cController::transaction`] and
+/// [`SyncI2cController::transaction`].
+///
+/// Each operation is written as `write(data)` or `read(buffer)`.
+///
+/// ```
+/// use embedded_hal_i2c::{AsyncI2cController, AsyncI2cTarget, AsyncReadTransaction, Transaction, ops};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let (mut controller, mut target) = simulator::simulator();
+///
+/// let controller_task = async move {
+///     let mut response = [0; 2];
+///     controller
+///         .transaction(0x42_u8, &mut ops![write(&[1, 2]), write(&[3]), read(&mut response)])
+///         .await
+///         .unwrap();
+///     assert_eq!(response, [4, 5]);
+/// };
+///
+/// let target_task = async move {
+///     for _ in 0..2 {
+///         let transaction = target.listen().await.unwrap();
+///         assert!(matches!(transaction, Transaction::Write { .. }));
+///         transaction.consume(0).await.unwrap();
+///     }
+///     let Ok(Transaction::Read { handler, .. }) = target.listen().await else {
+///         unreachable!()
+///     };
+///     handler.handle_complete(&[4, 5], 0xff).await.unwrap();
+///     assert!(matches!(target.listen().await.unwrap(), Transaction::Deselect));
+/// };
+///
+/// tokio::join!(controller_task, target_task);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! ops {
+    (@op write $data:expr) => {
+        $crate::Operation::Write($data)
+    };
+    (@op read $buffer:expr) => {
+        $crate::Operation::Read($buffer)
+    };
+    ($($kind:ident($buffer:expr)),* $(,)?) => {
+        [$($crate::ops!(@op $kind $buffer)),*]
+    };
+}
+
+/// Whether a transaction failing with `kind` is worth retrying as is.
+///
+/// A missing address acknowledgement usually means the target is busy, e.g.
+/// with an internal write cycle, and a lost arbitration means another
+/// controller used the bus. Both are transient. A missing data
+/// acknowledgement means the target refused the data, which a retry sends
+/// again. Bus faults need recovery before retrying, and the remaining errors
+/// cannot be classified.
+pub const fn is_retryable(kind: ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address) | ErrorKind::ArbitrationLoss
+    )
+}
+
+/// Whether `kind` is a fault on the bus itself, such as a misplaced start or
+/// stop condition, after which the bus may need recovery.
+pub const fn is_bus_fault(kind: ErrorKind) -> bool {
+    matches!(kind, ErrorKind::Bus)
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// An I2C slave address that is either a 7 bit or a ten bit address.
 pub enum AnyAddress {
     Seven(u8),
     Ten(u16),
 }
 
+impl AnyAddress {
+    /// Reconstruct a 10-bit address from the two address bytes sent on the
+    /// wire.
+    ///
+    /// `first` must be `0b11110_XX_R`, with the two most significant address
+    /// bits `XX` and the R/W bit `R`, which is ignored. `second` holds the
+    /// lower 8 bits. Returns `None` if the `11110` prefix is missing.
+    pub const fn from_ten_bit_bytes(first: u8, second: u8) -> Option<Self> {
+        if first & 0b1111_1000 != 0b1111_0000 {
+            return None;
+        }
+        let high = ((first >> 1) & 0b11) as u16;
+        Some(Self::Ten(high << 8 | second as u16))
+    }
+
+    /// The two address bytes of a 10-bit address as sent on the wire, with the
+    /// R/W bit cleared.
+    ///
+    /// Returns `None` for 7-bit addresses, and for 10-bit addresses that do
+    /// not fit in 10 bits.
+    pub const fn to_ten_bit_bytes(&self) -> Option<[u8; 2]> {
+        match *self {
+            Self::Ten(address) if address <= 0x3FF => {
+                let high = (address >> 8) as u8;
+                Some([0b1111_0000 | high << 1, address as u8])
+            }
+            _ => None,
+        }
+    }
+
+    /// The address value, without the R/W bit.
+    pub const fn raw(self) -> u16 {
+        match self {
+            Self::Seven(address) => address as u16,
+            Self::Ten(address) => address,
+        }
+    }
+
+    /// Whether this is a 10-bit address.
+    pub const fn is_ten_bit(self) -> bool {
+        matches!(self, Self::Ten(_))
+    }
+}
+
 impl From<SevenBitAddress> for AnyAddress {
     fn from(value: SevenBitAddress) -> Self {
         Self::Seven(value)
@@ -27,6 +172,42 @@ impl From<TenBitAddress> for AnyAddress {
     }
 }
 
+/// Converts the value of the address, also for a 10-bit address that fits in
+/// 7 bits. Note that such an address names a different device on the bus than
+/// the 7-bit address with the same value.
+///
+/// Fails, returning the original address, when the value does not fit in 7
+/// bits.
+impl TryFrom<AnyAddress> for SevenBitAddress {
+    type Error = AnyAddress;
+
+    fn try_from(value: AnyAddress) -> Result<Self, Self::Error> {
+        match value {
+            AnyAddress::Seven(address) if address <= 0x7F => Ok(address),
+            AnyAddress::Ten(address) if address <= 0x7F => Ok(address as u8),
+            AnyAddress::Seven(_) | AnyAddress::Ten(_) => Err(value),
+        }
+    }
+}
+
+/// Converts the value of the address, also for a 7-bit address. Note that
+/// such an address names a different device on the bus than the 10-bit address
+/// with the same value.
+///
+/// Fails, returning the original address, for 7-bit addresses that do not fit
+/// in 7 bits, and 10-bit addresses that do not fit in 10 bits.
+impl TryFrom<AnyAddress> for TenBitAddress {
+    type Error = AnyAddress;
+
+    fn try_from(value: AnyAddress) -> Result<Self, Self::Error> {
+        match value {
+            AnyAddress::Seven(address) if address <= 0x7F => Ok(address as u16),
+            AnyAddress::Ten(address) if address <= 0x3FF => Ok(address),
+            AnyAddress::Seven(_) | AnyAddress::Ten(_) => Err(value),
+        }
+    }
+}
+
 /// Transaction received from [`SyncI2cTarget::listen`] and
 /// [`AsyncI2cTarget::listen`]
 #[must_use = "Implicitly dropping a Transaction will NAK the request"]
@@ -57,11 +238,82 @@ pub enum Transaction<R, W> {
         /// Handler to be used in handling the transaction
         ///
         /// Dropping this handler nacks the address. Any other interaction
-        /// acknowledges the address.
+        /// acknowledges the address. A write without any data is acknowledged
+        /// with `handle_empty`.
         handler: W,
     },
 }
 
+impl<R, W> Transaction<R, W>
+where
+    R: AsyncReadTransaction,
+    W: AsyncWriteTransaction<Error = R::Error>,
+{
+    /// Handle the transaction without caring about its contents.
+    ///
+    /// Unlike dropping the transaction, this acknowledges everything: written
+    /// data is received and discarded, and reads are answered with `fill`
+    /// only.
+    pub async fn consume(self, fill: u8) -> Result<(), R::Error> {
+        match self {
+            Self::Deselect => {}
+            Self::Read { handler, .. } => {
+                handler.handle_complete(&[], fill).await?;
+            }
+            Self::Write { mut handler, .. } => {
+                let mut scratch = [0; 32];
+                while let WriteResult::Partial(next) = handler.handle_part(&mut scratch).await? {
+                    handler = next;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R, W> Transaction<R, W> {
+    /// The kind and address of this transaction, without the handler.
+    pub const fn tag(&self) -> TransactionTag {
+        match self {
+            Self::Deselect => TransactionTag::Deselect,
+            Self::Read { address, .. } => TransactionTag::Read(*address),
+            Self::Write { address, .. } => TransactionTag::Write(*address),
+        }
+    }
+
+    /// Convert the handler with `fr` or `fw`, e.g. to wrap it in a middleware
+    /// handler.
+    pub fn map_handlers<R2, W2>(
+        self,
+        fr: impl FnOnce(R) -> R2,
+        fw: impl FnOnce(W) -> W2,
+    ) -> Transaction<R2, W2> {
+        match self {
+            Self::Deselect => Transaction::Deselect,
+            Self::Read { address, handler } => Transaction::Read {
+                address,
+                handler: fr(handler),
+            },
+            Self::Write { address, handler } => Transaction::Write {
+                address,
+                handler: fw(handler),
+            },
+        }
+    }
+}
+
+/// Kind and address of a [`Transaction`], e.g. for diagnostics
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TransactionTag {
+    /// A [`Transaction::Deselect`]
+    Deselect,
+    /// A [`Transaction::Read`] for the given address
+    Read(AnyAddress),
+    /// A [`Transaction::Write`] for the given address
+    Write(AnyAddress),
+}
+
 /// Transaction received from [`SyncI2cTarget::listen_expect_read`] and
 /// [`AsyncI2cTarget::listen_expect_read`]
 #[must_use = "Implicitly dropping a Transaction will NAK the request"]
@@ -149,6 +401,12 @@ pub enum TransactionExpectWrite<R, W> {
 /// A transaction received from any of the [`SyncI2cTarget`] and [`AsyncI2cTarget`]'s listen functions.
 /// This type is intended to be used for simplifying control flow in users of
 /// the I2cTarget
+///
+/// Converting into this type is lossy: for the `Deselect`, `Read` and `Write`
+/// variants it is no longer known whether a read or a write was expected. The
+/// `TryFrom` implementations for the other transaction types only fail on the
+/// variants that cannot be represented by them.
+#[must_use = "Implicitly dropping a Transaction will NAK the request"]
 pub enum TransactionExpectEither<R, W> {
     /// A read transaction was received for the expected address, and the
     /// entire transaction could be handled using the bytes provided.
@@ -195,6 +453,91 @@ pub enum TransactionExpectEither<R, W> {
     },
 }
 
+impl<R, W> TransactionExpectRead<R, W> {
+    /// Convert the handler with `fr` or `fw`, e.g. to wrap it in a middleware
+    /// handler.
+    pub fn map_handlers<R2, W2>(
+        self,
+        fr: impl FnOnce(R) -> R2,
+        fw: impl FnOnce(W) -> W2,
+    ) -> TransactionExpectRead<R2, W2> {
+        use TransactionExpectRead as T;
+        match self {
+            Self::ExpectedCompleteRead { size } => T::ExpectedCompleteRead { size },
+            Self::ExpectedPartialRead { handler } => T::ExpectedPartialRead {
+                handler: fr(handler),
+            },
+            Self::Deselect => T::Deselect,
+            Self::Read { address, handler } => T::Read {
+                address,
+                handler: fr(handler),
+            },
+            Self::Write { address, handler } => T::Write {
+                address,
+                handler: fw(handler),
+            },
+        }
+    }
+}
+
+impl<R, W> TransactionExpectWrite<R, W> {
+    /// Convert the handler with `fr` or `fw`, e.g. to wrap it in a middleware
+    /// handler.
+    pub fn map_handlers<R2, W2>(
+        self,
+        fr: impl FnOnce(R) -> R2,
+        fw: impl FnOnce(W) -> W2,
+    ) -> TransactionExpectWrite<R2, W2> {
+        use TransactionExpectWrite as T;
+        match self {
+            Self::ExpectedCompleteWrite { size } => T::ExpectedCompleteWrite { size },
+            Self::ExpectedPartialWrite { handler } => T::ExpectedPartialWrite {
+                handler: fw(handler),
+            },
+            Self::Deselect => T::Deselect,
+            Self::Read { address, handler } => T::Read {
+                address,
+                handler: fr(handler),
+            },
+            Self::Write { address, handler } => T::Write {
+                address,
+                handler: fw(handler),
+            },
+        }
+    }
+}
+
+impl<R, W> TransactionExpectEither<R, W> {
+    /// Convert the handler with `fr` or `fw`, e.g. to wrap it in a middleware
+    /// handler.
+    pub fn map_handlers<R2, W2>(
+        self,
+        fr: impl FnOnce(R) -> R2,
+        fw: impl FnOnce(W) -> W2,
+    ) -> TransactionExpectEither<R2, W2> {
+        use TransactionExpectEither as T;
+        match self {
+            Self::ExpectedCompleteRead { size } => T::ExpectedCompleteRead { size },
+            Self::ExpectedPartialRead { handler } => T::ExpectedPartialRead {
+                handler: fr(handler),
+            },
+            Self::ExpectedCompleteWrite { size } => T::ExpectedCompleteWrite { size },
+            Self::ExpectedPartialWrite { handler } => T::ExpectedPartialWrite {
+                handler: fw(handler),
+            },
+            Self::Deselect => T::Deselect,
+            Self::Read { address, handler } => T::Read {
+                address,
+                handler: fr(handler),
+            },
+            Self::Write { address, handler } => T::Write {
+                address,
+                handler: fw(handler),
+            },
+        }
+    }
+}
+
 impl<R, W> From<Transaction<R, W>> for TransactionExpectRead<R, W> {
     fn from(value: Transaction<R, W>) -> Self {
         match value {
@@ -257,6 +600,97 @@ impl<R, W> From<TransactionExpectWrite<R, W>> for TransactionExpectEither<R, W>
     }
 }
 
+/// Recover the [`TransactionExpectRead`] a [`TransactionExpectEither`] may have
+/// been converted from. Fails, returning the original value, for the
+/// write-expectation variants.
+impl<R, W> TryFrom<TransactionExpectEither<R, W>> for TransactionExpectRead<R, W> {
+    type Error = TransactionExpectEither<R, W>;
+
+    fn try_from(value: TransactionExpectEither<R, W>) -> Result<Self, Self::Error> {
+        Ok(match value {
+            TransactionExpectEither::ExpectedCompleteRead { size } => {
+                Self::ExpectedCompleteRead { size }
+            }
+            TransactionExpectEither::ExpectedPartialRead { handler } => {
+                Self::ExpectedPartialRead { handler }
+            }
+            TransactionExpectEither::Deselect => Self::Deselect,
+            TransactionExpectEither::Read { address, handler } => Self::Read { address, handler },
+            TransactionExpectEither::Write { address, handler } => Self::Write { address, handler },
+            other @ (TransactionExpectEither::ExpectedCompleteWrite { .. }
+            | TransactionExpectEither::ExpectedPartialWrite { .. }) => return Err(other),
+        })
+    }
+}
+
+/// Recover the [`TransactionExpectWrite`] a [`TransactionExpectEither`] may
+/// have been converted from. Fails, returning the original value, for the
+/// read-expectation variants.
+impl<R, W> TryFrom<TransactionExpectEither<R, W>> for TransactionExpectWrite<R, W> {
+    type Error = TransactionExpectEither<R, W>;
+
+    fn try_from(value: TransactionExpectEither<R, W>) -> Result<Self, Self::Error> {
+        Ok(match value {
+            TransactionExpectEither::ExpectedCompleteWrite { size } => {
+                Self::ExpectedCompleteWrite { size }
+            }
+            TransactionExpectEither::ExpectedPartialWrite { handler } => {
+                Self::ExpectedPartialWrite { handler }
+            }
+            TransactionExpectEither::Deselect => Self::Deselect,
+            TransactionExpectEither::Read { address, handler } => Self::Read { address, handler },
+            TransactionExpectEither::Write { address, handler } => Self::Write { address, handler },
+            other @ (TransactionExpectEither::ExpectedCompleteRead { .. }
+            | TransactionExpectEither::ExpectedPartialRead { .. }) => return Err(other),
+        })
+    }
+}
+
+/// Recover a plain [`Transaction`]. Fails, returning the original value, if
+/// the expected transaction was already handled.
+impl<R, W> TryFrom<TransactionExpectEither<R, W>> for Transaction<R, W> {
+    type Error = TransactionExpectEither<R, W>;
+
+    fn try_from(value: TransactionExpectEither<R, W>) -> Result<Self, Self::Error> {
+        Ok(match value {
+            TransactionExpectEither::Deselect => Self::Deselect,
+            TransactionExpectEither::Read { address, handler } => Self::Read { address, handler },
+            TransactionExpectEither::Write { address, handler } => Self::Write { address, handler },
+            other => return Err(other),
+        })
+    }
+}
+
+/// Recover a plain [`Transaction`]. Fails, returning the original value, if
+/// the expected read was already handled.
+impl<R, W> TryFrom<TransactionExpectRead<R, W>> for Transaction<R, W> {
+    type Error = TransactionExpectRead<R, W>;
+
+    fn try_from(value: TransactionExpectRead<R, W>) -> Result<Self, Self::Error> {
+        Ok(match value {
+            TransactionExpectRead::Deselect => Self::Deselect,
+            TransactionExpectRead::Read { address, handler } => Self::Read { address, handler },
+            TransactionExpectRead::Write { address, handler } => Self::Write { address, handler },
+            other => return Err(other),
+        })
+    }
+}
+
+/// Recover a plain [`Transaction`]. Fails, returning the original value, if
+/// the expected write was already handled.
+impl<R, W> TryFrom<TransactionExpectWrite<R, W>> for Transaction<R, W> {
+    type Error = TransactionExpectWrite<R, W>;
+
+    fn try_from(value: TransactionExpectWrite<R, W>) -> Result<Self, Self::Error> {
+        Ok(match value {
+            TransactionExpectWrite::Deselect => Self::Deselect,
+            TransactionExpectWrite::Read { address, handler } => Self::Read { address, handler },
+            TransactionExpectWrite::Write { address, handler } => Self::Write { address, handler },
+            other => return Err(other),
+        })
+    }
+}
+
 /// Result of partial handling of a read transaction, see also
 /// [`SyncReadTransaction::handle_part`] and
 /// [`AsyncReadTransaction::handle_part`]
@@ -269,6 +703,27 @@ pub enum ReadResult<R> {
     Complete(usize),
 }
 
+impl<R> ReadResult<R> {
+    /// The number of bytes of the final part if the transaction was
+    /// completed, `None` if more bytes are needed.
+    pub const fn completed(&self) -> Option<usize> {
+        match self {
+            Self::Partial(_) => None,
+            Self::Complete(size) => Some(*size),
+        }
+    }
+
+    /// Whether the handler was returned to handle more bytes.
+    pub const fn is_partial(&self) -> bool {
+        matches!(self, Self::Partial(_))
+    }
+
+    /// Whether the transaction was completed.
+    pub const fn is_complete(&self) -> bool {
+        matches!(self, Self::Complete(_))
+    }
+}
+
 /// Result of partial handling of a write transaction, see also
 /// [`SyncWriteTransaction::handle_part`] and
 /// [`AsyncWriteTransaction::handle_part`]
@@ -282,6 +737,27 @@ pub enum WriteResult<W> {
     Complete(usize),
 }
 
+impl<W> WriteResult<W> {
+    /// The number of bytes of the final part if the transaction was
+    /// completed, `None` if more bytes are needed.
+    pub const fn completed(&self) -> Option<usize> {
+        match self {
+            Self::Partial(_) => None,
+            Self::Complete(size) => Some(*size),
+        }
+    }
+
+    /// Whether the handler was returned to handle more bytes.
+    pub const fn is_partial(&self) -> bool {
+        matches!(self, Self::Partial(_))
+    }
+
+    /// Whether the transaction was completed.
+    pub const fn is_complete(&self) -> bool {
+        matches!(self, Self::Complete(_))
+    }
+}
+
 /// I2c device implementing I2c target functionality in a synchronous fashion.
 pub trait SyncI2cTarget {
     type Error;
@@ -295,8 +771,66 @@ pub trait SyncI2cTarget {
     /// Listen for a new transaction to occur
     fn listen(&mut self) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error>;
 
+    /// The addresses this target is currently configured to respond to.
+    ///
+    /// An empty iterator means the addresses are not known, for example
+    /// because the target responds to any address. The default implementation
+    /// always returns an empty iterator.
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        core::iter::empty()
+    }
+
+    /// The number of operations the controller still intends to perform in the
+    /// current transaction, e.g. `Some(1)` after handling the write of a
+    /// write-read.
+    ///
+    /// Real hardware cannot know what the controller will do next, so this is
+    /// only meant for simulated targets to check the handling of combined
+    /// transactions in tests. The default implementation always returns
+    /// `None`, meaning the number is not known.
+    fn ops_remaining(&self) -> Option<usize> {
+        None
+    }
+
+    /// The lowest and highest bus clock this target supports, in Hz.
+    ///
+    /// Drivers can use this to pick a bus speed. The default implementation
+    /// always returns `None`, meaning the range is not known.
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    /// Whether the device was reset since the last call, e.g. by a power-on
+    /// reset of the peripheral. Any transaction in progress was aborted, and
+    /// state kept by the application on behalf of the device should be
+    /// cleared.
+    ///
+    /// The default implementation always returns `false`, for targets that
+    /// cannot detect a reset.
+    fn take_reset(&mut self) -> bool {
+        false
+    }
+
+    /// Listen for a new transaction to occur, giving up after `timeout` has
+    /// passed without one. Returns `Ok(None)` on timeout.
+    ///
+    /// This allows a polled main loop to service the bus without blocking
+    /// forever. The default implementation does not support waiting, and
+    /// returns `Ok(None)` immediately.
+    #[allow(clippy::type_complexity)]
+    fn listen_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<Transaction<Self::Read<'_>, Self::Write<'_>>>, Self::Error> {
+        let _ = timeout;
+        Ok(None)
+    }
+
     /// Listen for a new transaction to occur, expecting a write. Using this
     /// function may allow some hardware to handle the write more efficiently.
+    ///
+    /// The expected address is compared against the exact address used by the
+    /// controller, also when the target responds to multiple addresses.
     fn listen_expect_write<'a>(
         &'a mut self,
         expected_address: AnyAddress,
@@ -318,6 +852,9 @@ pub trait SyncI2cTarget {
     }
     /// Listen for a new transaction to occur, expecting a read. Using this
     /// function may allow some hardware to handle the read more efficiently.
+    ///
+    /// The expected address is compared against the exact address used by the
+    /// controller, also when the target responds to multiple addresses.
     fn listen_expect_read<'a>(
         &'a mut self,
         expected_address: AnyAddress,
@@ -337,6 +874,107 @@ pub trait SyncI2cTarget {
             other => Ok(other.into()),
         }
     }
+    /// Listen for a new transaction to occur, expecting either a read or a
+    /// write. The read buffer is used when a read arrives for the expected
+    /// address, the write buffer when a write does.
+    ///
+    /// The expected address is compared against the exact address used by the
+    /// controller, also when the target responds to multiple addresses.
+    fn listen_expect_either<'a>(
+        &'a mut self,
+        expected_address: AnyAddress,
+        read_buffer: &[u8],
+        write_buffer: &mut [u8],
+    ) -> Result<TransactionExpectEither<Self::Read<'a>, Self::Write<'a>>, Self::Error> {
+        match self.listen()? {
+            Transaction::Read { address, handler } if address == expected_address => {
+                match handler.handle_part(read_buffer)? {
+                    ReadResult::Complete(size) => {
+                        Ok(TransactionExpectEither::ExpectedCompleteRead { size })
+                    }
+                    ReadResult::Partial(handler) => {
+                        Ok(TransactionExpectEither::ExpectedPartialRead { handler })
+                    }
+                }
+            }
+            Transaction::Write { address, handler } if address == expected_address => {
+                match handler.handle_part(write_buffer)? {
+                    WriteResult::Complete(size) => {
+                        Ok(TransactionExpectEither::ExpectedCompleteWrite { size })
+                    }
+                    WriteResult::Partial(handler) => {
+                        Ok(TransactionExpectEither::ExpectedPartialWrite { handler })
+                    }
+                }
+            }
+            other => Ok(other.into()),
+        }
+    }
+}
+
+impl<T: SyncI2cTarget + ?Sized> SyncI2cTarget for &mut T {
+    type Error = T::Error;
+    type Read<'a>
+        = T::Read<'a>
+    where
+        Self: 'a;
+    type Write<'a>
+        = T::Write<'a>
+    where
+        Self: 'a;
+
+    fn listen(&mut self) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        T::listen(self)
+    }
+
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        T::addresses(self)
+    }
+
+    fn ops_remaining(&self) -> Option<usize> {
+        T::ops_remaining(self)
+    }
+
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        T::clock_range(self)
+    }
+
+    fn take_reset(&mut self) -> bool {
+        T::take_reset(self)
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn listen_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<Transaction<Self::Read<'_>, Self::Write<'_>>>, Self::Error> {
+        T::listen_timeout(self, timeout)
+    }
+
+    fn listen_expect_write<'a>(
+        &'a mut self,
+        expected_address: AnyAddress,
+        write_buffer: &mut [u8],
+    ) -> Result<TransactionExpectWrite<Self::Read<'a>, Self::Write<'a>>, Self::Error> {
+        T::listen_expect_write(self, expected_address, write_buffer)
+    }
+
+    fn listen_expect_read<'a>(
+        &'a mut self,
+        expected_address: AnyAddress,
+        read_buffer: &[u8],
+    ) -> Result<TransactionExpectRead<Self::Read<'a>, Self::Write<'a>>, Self::Error> {
+        T::listen_expect_read(self, expected_address, read_buffer)
+    }
+
+    fn listen_expect_either<'a>(
+        &'a mut self,
+        expected_address: AnyAddress,
+        read_buffer: &[u8],
+        write_buffer: &mut [u8],
+    ) -> Result<TransactionExpectEither<Self::Read<'a>, Self::Write<'a>>, Self::Error> {
+        T::listen_expect_either(self, expected_address, read_buffer, write_buffer)
+    }
 }
 
 /// Handler for a synchronous read transaction
@@ -351,6 +989,30 @@ pub trait SyncReadTransaction: Sized {
     /// should this not be sufficient.
     fn handle_part(self, buffer: &[u8]) -> Result<ReadResult<Self>, Self::Error>;
 
+    /// Like `handle_part`, but also pass the bytes read by the master to
+    /// `tap`, e.g. to log them while debugging.
+    fn handle_part_tap(
+        self,
+        buffer: &[u8],
+        mut tap: impl FnMut(&[u8]),
+    ) -> Result<ReadResult<Self>, Self::Error> {
+        let result = self.handle_part(buffer)?;
+        let size = match &result {
+            ReadResult::Complete(size) => *size,
+            ReadResult::Partial(_) => buffer.len(),
+        };
+        tap(&buffer[..size]);
+        Ok(result)
+    }
+
+    /// The maximum number of overrun characters the default implementation of
+    /// `handle_complete` provides one at a time.
+    ///
+    /// Once reached, the handler is dropped and the rest of the read is filled
+    /// with the implementation-defined overrun character. This bounds the work
+    /// done for a controller that keeps reading. Unbounded by default.
+    const MAX_OVERRUN: usize = usize::MAX;
+
     /// Send the buffer to the master as part of the read transaction, then
     /// complete it by providing the overrun character for the remainder of the
     /// read transaction until the master ends it.
@@ -362,17 +1024,58 @@ pub trait SyncReadTransaction: Sized {
             ReadResult::Complete(size) => Ok(size),
             ReadResult::Partial(mut this) => {
                 let mut total = buffer.len();
-                loop {
+                for _ in 0..Self::MAX_OVERRUN {
                     match this.handle_part(&[ovc])? {
-                        ReadResult::Complete(extra) => break Ok(total + extra),
+                        ReadResult::Complete(extra) => return Ok(total + extra),
                         ReadResult::Partial(handler) => {
                             this = handler;
                             total += 1;
                         }
                     }
                 }
+                // Leave the rest of the overrun to the implementation.
+                drop(this);
+                Ok(total)
+            }
+        }
+    }
+
+    /// End the read transaction early, providing `tail` for the remainder of
+    /// the read instead of the implementation-defined overrun character.
+    /// Returns the number of `tail` bytes read by the master.
+    ///
+    /// The default implementation provides `tail` one byte at a time, up to
+    /// `MAX_OVERRUN` bytes. Implementations may want to override it to provide
+    /// better performance.
+    fn finish_with(self, tail: u8) -> Result<usize, Self::Error> {
+        self.handle_complete(&[], tail)
+    }
+
+    /// Send `data` to the master in chunks of at most `chunk` bytes, each
+    /// provided through a separate call to `handle_part`. Returns the number
+    /// of bytes actually read by the master, which may be less than
+    /// `data.len()` if it ended the transaction early.
+    ///
+    /// Should the master read beyond the end of `data`, the handler is dropped
+    /// and the rest of the read is filled with the implementation-defined
+    /// overrun character.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk` is 0.
+    fn handle_chunked(self, data: &[u8], chunk: usize) -> Result<usize, Self::Error> {
+        let mut this = self;
+        let mut total = 0;
+        for part in data.chunks(chunk) {
+            match this.handle_part(part)? {
+                ReadResult::Complete(size) => return Ok(total + size),
+                ReadResult::Partial(handler) => {
+                    this = handler;
+                    total += part.len();
+                }
             }
         }
+        Ok(total)
     }
 }
 
@@ -386,6 +1089,39 @@ pub trait SyncWriteTransaction: Sized {
     /// byte. The last byte is neither acknowledged nor not acknowledged.
     fn handle_part(self, buffer: &mut [u8]) -> Result<WriteResult<Self>, Self::Error>;
 
+    /// Like `handle_part`, but also pass the received bytes to `tap`, e.g. to
+    /// log them while debugging.
+    fn handle_part_tap(
+        self,
+        buffer: &mut [u8],
+        mut tap: impl FnMut(&[u8]),
+    ) -> Result<WriteResult<Self>, Self::Error> {
+        let result = self.handle_part(buffer)?;
+        let size = match &result {
+            WriteResult::Complete(size) => *size,
+            WriteResult::Partial(_) => buffer.len(),
+        };
+        tap(&buffer[..size]);
+        Ok(result)
+    }
+
+    /// Like `handle_part`, but also return how many bytes of `buffer` were
+    /// acknowledged.
+    ///
+    /// On a partial result this is one less than `buffer.len()`, as the last
+    /// byte is still pending. An empty buffer acknowledges nothing. On a
+    /// complete result all received bytes are acknowledged.
+    fn handle_part_counted(
+        self,
+        buffer: &mut [u8],
+    ) -> Result<(usize, WriteResult<Self>), Self::Error> {
+        let len = buffer.len();
+        Ok(match self.handle_part(buffer)? {
+            WriteResult::Complete(size) => (size, WriteResult::Complete(size)),
+            WriteResult::Partial(handler) => (len.saturating_sub(1), WriteResult::Partial(handler)),
+        })
+    }
+
     /// Accept buffer.len bytes of the write, acknowledging all these bytes.
     /// Should the master try to send more bytes than fit in the buffer, any
     /// overrun is not acknowledged.
@@ -402,6 +1138,78 @@ pub trait SyncWriteTransaction: Sized {
             }
         }
     }
+
+    /// Receive the write in pieces of up to `chunk.len()` bytes, passing each
+    /// to `f` as it arrives, until the master ends the transaction. Returns the
+    /// total number of bytes received.
+    ///
+    /// This handles writes of any length with a small buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk` is empty.
+    fn drain_write(self, chunk: &mut [u8], mut f: impl FnMut(&[u8])) -> Result<usize, Self::Error> {
+        assert!(!chunk.is_empty(), "chunk must not be empty");
+        let mut this = self;
+        let mut total = 0;
+        loop {
+            match this.handle_part(chunk)? {
+                WriteResult::Complete(size) => {
+                    if size > 0 {
+                        f(&chunk[..size]);
+                    }
+                    return Ok(total + size);
+                }
+                WriteResult::Partial(handler) => {
+                    f(chunk);
+                    this = handler;
+                    total += chunk.len();
+                }
+            }
+        }
+    }
+
+    /// Acknowledge the address of a write without any data, such as an SMBus
+    /// quick command.
+    ///
+    /// Unlike dropping the handler, which does not acknowledge the address,
+    /// this acknowledges the address and completes the transaction. Should the
+    /// controller send data anyway, the first byte is not acknowledged.
+    fn handle_empty(self) -> Result<(), Self::Error> {
+        if let WriteResult::Partial(handler) = self.handle_part(&mut [0])? {
+            drop(handler);
+        }
+        Ok(())
+    }
+
+    /// Acknowledge the address, then receive the first data byte without
+    /// acknowledging it, e.g. to reject a command byte. The controller sees the
+    /// write fail with a data NAK.
+    ///
+    /// A write without any data has no byte to reject, and is completed as
+    /// usual. To look at the byte before rejecting it, call `handle_part` with
+    /// a single byte buffer and drop the returned handler instead.
+    fn reject_first_byte(self) -> Result<(), Self::Error> {
+        match self.handle_part(&mut [0])? {
+            // Dropping does not acknowledge the pending byte
+            WriteResult::Partial(handler) => drop(handler),
+            WriteResult::Complete(_) => {}
+        }
+        Ok(())
+    }
+
+    /// End the write transaction without accepting any further bytes, like
+    /// dropping the handler does: the pending last byte is not acknowledged,
+    /// or the address if no data was handled yet. Returns the number of bytes
+    /// received by this call, which is always 0, like `finish_with` on read
+    /// handlers returns the number of tail bytes.
+    ///
+    /// Unlike dropping, this lets implementations report errors while ending
+    /// the transaction. The default implementation drops the handler.
+    fn finish(self) -> Result<usize, Self::Error> {
+        drop(self);
+        Ok(0)
+    }
 }
 
 /// I2c device implementing I2c target functionality for async runtimes.
@@ -418,8 +1226,51 @@ pub trait AsyncI2cTarget {
     async fn listen(&mut self)
     -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error>;
 
+    /// The addresses this target is currently configured to respond to.
+    ///
+    /// An empty iterator means the addresses are not known, for example
+    /// because the target responds to any address. The default implementation
+    /// always returns an empty iterator.
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        core::iter::empty()
+    }
+
+    /// The number of operations the controller still intends to perform in the
+    /// current transaction, e.g. `Some(1)` after handling the write of a
+    /// write-read.
+    ///
+    /// Real hardware cannot know what the controller will do next, so this is
+    /// only meant for simulated targets to check the handling of combined
+    /// transactions in tests. The default implementation always returns
+    /// `None`, meaning the number is not known.
+    fn ops_remaining(&self) -> Option<usize> {
+        None
+    }
+
+    /// The lowest and highest bus clock this target supports, in Hz.
+    ///
+    /// Drivers can use this to pick a bus speed. The default implementation
+    /// always returns `None`, meaning the range is not known.
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    /// Whether the device was reset since the last call, e.g. by a power-on
+    /// reset of the peripheral. Any transaction in progress was aborted, and
+    /// state kept by the application on behalf of the device should be
+    /// cleared.
+    ///
+    /// The default implementation always returns `false`, for targets that
+    /// cannot detect a reset.
+    fn take_reset(&mut self) -> bool {
+        false
+    }
+
     /// Listen for a new transaction to occur, expecting a write. Using this
     /// function may allow some hardware to handle the write more efficiently.
+    ///
+    /// The expected address is compared against the exact address used by the
+    /// controller, also when the target responds to multiple addresses.
     async fn listen_expect_write<'a>(
         &'a mut self,
         expected_address: AnyAddress,
@@ -441,6 +1292,9 @@ pub trait AsyncI2cTarget {
     }
     /// Listen for a new transaction to occur, expecting a read. Using this
     /// function may allow some hardware to handle the read more efficiently.
+    ///
+    /// The expected address is compared against the exact address used by the
+    /// controller, also when the target responds to multiple addresses.
     async fn listen_expect_read<'a>(
         &'a mut self,
         expected_address: AnyAddress,
@@ -460,6 +1314,101 @@ pub trait AsyncI2cTarget {
             other => Ok(other.into()),
         }
     }
+    /// Listen for a new transaction to occur, expecting either a read or a
+    /// write. The read buffer is used when a read arrives for the expected
+    /// address, the write buffer when a write does.
+    ///
+    /// The expected address is compared against the exact address used by the
+    /// controller, also when the target responds to multiple addresses.
+    async fn listen_expect_either<'a>(
+        &'a mut self,
+        expected_address: AnyAddress,
+        read_buffer: &[u8],
+        write_buffer: &mut [u8],
+    ) -> Result<TransactionExpectEither<Self::Read<'a>, Self::Write<'a>>, Self::Error> {
+        match self.listen().await? {
+            Transaction::Read { address, handler } if address == expected_address => {
+                match handler.handle_part(read_buffer).await? {
+                    ReadResult::Complete(size) => {
+                        Ok(TransactionExpectEither::ExpectedCompleteRead { size })
+                    }
+                    ReadResult::Partial(handler) => {
+                        Ok(TransactionExpectEither::ExpectedPartialRead { handler })
+                    }
+                }
+            }
+            Transaction::Write { address, handler } if address == expected_address => {
+                match handler.handle_part(write_buffer).await? {
+                    WriteResult::Complete(size) => {
+                        Ok(TransactionExpectEither::ExpectedCompleteWrite { size })
+                    }
+                    WriteResult::Partial(handler) => {
+                        Ok(TransactionExpectEither::ExpectedPartialWrite { handler })
+                    }
+                }
+            }
+            other => Ok(other.into()),
+        }
+    }
+}
+
+impl<T: AsyncI2cTarget + ?Sized> AsyncI2cTarget for &mut T {
+    type Error = T::Error;
+    type Read<'a>
+        = T::Read<'a>
+    where
+        Self: 'a;
+    type Write<'a>
+        = T::Write<'a>
+    where
+        Self: 'a;
+
+    async fn listen(
+        &mut self,
+    ) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        T::listen(self).await
+    }
+
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        T::addresses(self)
+    }
+
+    fn ops_remaining(&self) -> Option<usize> {
+        T::ops_remaining(self)
+    }
+
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        T::clock_range(self)
+    }
+
+    fn take_reset(&mut self) -> bool {
+        T::take_reset(self)
+    }
+
+    async fn listen_expect_write<'a>(
+        &'a mut self,
+        expected_address: AnyAddress,
+        write_buffer: &mut [u8],
+    ) -> Result<TransactionExpectWrite<Self::Read<'a>, Self::Write<'a>>, Self::Error> {
+        T::listen_expect_write(self, expected_address, write_buffer).await
+    }
+
+    async fn listen_expect_read<'a>(
+        &'a mut self,
+        expected_address: AnyAddress,
+        read_buffer: &[u8],
+    ) -> Result<TransactionExpectRead<Self::Read<'a>, Self::Write<'a>>, Self::Error> {
+        T::listen_expect_read(self, expected_address, read_buffer).await
+    }
+
+    async fn listen_expect_either<'a>(
+        &'a mut self,
+        expected_address: AnyAddress,
+        read_buffer: &[u8],
+        write_buffer: &mut [u8],
+    ) -> Result<TransactionExpectEither<Self::Read<'a>, Self::Write<'a>>, Self::Error> {
+        T::listen_expect_either(self, expected_address, read_buffer, write_buffer).await
+    }
 }
 
 /// Handler for an asynchronous read transaction
@@ -474,6 +1423,30 @@ pub trait AsyncReadTransaction: Sized {
     /// should this not be sufficient.
     async fn handle_part(self, buffer: &[u8]) -> Result<ReadResult<Self>, Self::Error>;
 
+    /// Like `handle_part`, but also pass the bytes read by the master to
+    /// `tap`, e.g. to log them while debugging.
+    async fn handle_part_tap(
+        self,
+        buffer: &[u8],
+        mut tap: impl FnMut(&[u8]),
+    ) -> Result<ReadResult<Self>, Self::Error> {
+        let result = self.handle_part(buffer).await?;
+        let size = match &result {
+            ReadResult::Complete(size) => *size,
+            ReadResult::Partial(_) => buffer.len(),
+        };
+        tap(&buffer[..size]);
+        Ok(result)
+    }
+
+    /// The maximum number of overrun characters the default implementation of
+    /// `handle_complete` provides one at a time.
+    ///
+    /// Once reached, the handler is dropped and the rest of the read is filled
+    /// with the implementation-defined overrun character. This bounds the work
+    /// done for a controller that keeps reading. Unbounded by default.
+    const MAX_OVERRUN: usize = usize::MAX;
+
     /// Send the buffer to the master as part of the read transaction, then
     /// complete it by providing the overrun character for the remainder of the
     /// read transaction until the master ends it.
@@ -485,15 +1458,93 @@ pub trait AsyncReadTransaction: Sized {
             ReadResult::Complete(size) => Ok(size),
             ReadResult::Partial(mut this) => {
                 let mut total = buffer.len();
-                loop {
+                for _ in 0..Self::MAX_OVERRUN {
                     match this.handle_part(&[ovc]).await? {
-                        ReadResult::Complete(extra) => break Ok(total + extra),
+                        ReadResult::Complete(extra) => return Ok(total + extra),
                         ReadResult::Partial(handler) => {
                             this = handler;
                             total += 1;
                         }
                     }
                 }
+                // Leave the rest of the overrun to the implementation.
+                drop(this);
+                Ok(total)
+            }
+        }
+    }
+
+    /// End the read transaction early, providing `tail` for the remainder of
+    /// the read instead of the implementation-defined overrun character.
+    /// Returns the number of `tail` bytes read by the master.
+    ///
+    /// The default implementation provides `tail` one byte at a time, up to
+    /// `MAX_OVERRUN` bytes. Implementations may want to override it to provide
+    /// better performance.
+    async fn finish_with(self, tail: u8) -> Result<usize, Self::Error> {
+        self.handle_complete(&[], tail).await
+    }
+
+    /// Send `data` to the master in chunks of at most `chunk` bytes, each
+    /// provided through a separate call to `handle_part`. Returns the number
+    /// of bytes actually read by the master, which may be less than
+    /// `data.len()` if it ended the transaction early.
+    ///
+    /// Should the master read beyond the end of `data`, the handler is dropped
+    /// and the rest of the read is filled with the implementation-defined
+    /// overrun character.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk` is 0.
+    async fn handle_chunked(self, data: &[u8], chunk: usize) -> Result<usize, Self::Error> {
+        let mut this = self;
+        let mut total = 0;
+        for part in data.chunks(chunk) {
+            match this.handle_part(part).await? {
+                ReadResult::Complete(size) => return Ok(total + size),
+                ReadResult::Partial(handler) => {
+                    this = handler;
+                    total += part.len();
+                }
+            }
+        }
+        Ok(total)
+    }
+
+    /// Send data produced by `refill`, e.g. fetched from slow storage, to the
+    /// master. Returns the number of bytes provided by `refill` that were read
+    /// by the master. Unlike with `handle_complete`, overrun characters are not
+    /// counted.
+    ///
+    /// `refill` is awaited to fill `buffer` with the next chunk of data,
+    /// returning the number of bytes it wrote. Each chunk is provided through a
+    /// separate call to `handle_part`. Once `refill` returns 0, or the master
+    /// ends the transaction, no more chunks are fetched. Should the master read
+    /// beyond the end of the data, the handler is dropped and the rest of the
+    /// read is filled with the implementation-defined overrun character.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `refill` returns more than the length of `buffer`.
+    async fn handle_async(
+        self,
+        buffer: &mut [u8],
+        mut refill: impl AsyncFnMut(&mut [u8]) -> usize,
+    ) -> Result<usize, Self::Error> {
+        let mut this = self;
+        let mut total = 0;
+        loop {
+            let len = refill(buffer).await;
+            if len == 0 {
+                return Ok(total);
+            }
+            match this.handle_part(&buffer[..len]).await? {
+                ReadResult::Complete(size) => return Ok(total + size),
+                ReadResult::Partial(handler) => {
+                    this = handler;
+                    total += len;
+                }
             }
         }
     }
@@ -509,6 +1560,39 @@ pub trait AsyncWriteTransaction: Sized {
     /// byte. The last byte is neither acknowledged nor not acknowledged.
     async fn handle_part(self, buffer: &mut [u8]) -> Result<WriteResult<Self>, Self::Error>;
 
+    /// Like `handle_part`, but also pass the received bytes to `tap`, e.g. to
+    /// log them while debugging.
+    async fn handle_part_tap(
+        self,
+        buffer: &mut [u8],
+        mut tap: impl FnMut(&[u8]),
+    ) -> Result<WriteResult<Self>, Self::Error> {
+        let result = self.handle_part(buffer).await?;
+        let size = match &result {
+            WriteResult::Complete(size) => *size,
+            WriteResult::Partial(_) => buffer.len(),
+        };
+        tap(&buffer[..size]);
+        Ok(result)
+    }
+
+    /// Like `handle_part`, but also return how many bytes of `buffer` were
+    /// acknowledged.
+    ///
+    /// On a partial result this is one less than `buffer.len()`, as the last
+    /// byte is still pending. An empty buffer acknowledges nothing. On a
+    /// complete result all received bytes are acknowledged.
+    async fn handle_part_counted(
+        self,
+        buffer: &mut [u8],
+    ) -> Result<(usize, WriteResult<Self>), Self::Error> {
+        let len = buffer.len();
+        Ok(match self.handle_part(buffer).await? {
+            WriteResult::Complete(size) => (size, WriteResult::Complete(size)),
+            WriteResult::Partial(handler) => (len.saturating_sub(1), WriteResult::Partial(handler)),
+        })
+    }
+
     /// Accept buffer.len bytes of the write, acknowledging all these bytes.
     /// Should the master try to send more bytes than fit in the buffer, any
     /// overrun is not acknowledged.
@@ -525,4 +1609,80 @@ pub trait AsyncWriteTransaction: Sized {
             }
         }
     }
+
+    /// Receive the write in pieces of up to `chunk.len()` bytes, passing each
+    /// to `f` as it arrives, until the master ends the transaction. Returns the
+    /// total number of bytes received.
+    ///
+    /// This handles writes of any length with a small buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk` is empty.
+    async fn drain_write(
+        self,
+        chunk: &mut [u8],
+        mut f: impl FnMut(&[u8]),
+    ) -> Result<usize, Self::Error> {
+        assert!(!chunk.is_empty(), "chunk must not be empty");
+        let mut this = self;
+        let mut total = 0;
+        loop {
+            match this.handle_part(chunk).await? {
+                WriteResult::Complete(size) => {
+                    if size > 0 {
+                        f(&chunk[..size]);
+                    }
+                    return Ok(total + size);
+                }
+                WriteResult::Partial(handler) => {
+                    f(chunk);
+                    this = handler;
+                    total += chunk.len();
+                }
+            }
+        }
+    }
+
+    /// Acknowledge the address of a write without any data, such as an SMBus
+    /// quick command.
+    ///
+    /// Unlike dropping the handler, which does not acknowledge the address,
+    /// this acknowledges the address and completes the transaction. Should the
+    /// controller send data anyway, the first byte is not acknowledged.
+    async fn handle_empty(self) -> Result<(), Self::Error> {
+        if let WriteResult::Partial(handler) = self.handle_part(&mut [0]).await? {
+            drop(handler);
+        }
+        Ok(())
+    }
+
+    /// Acknowledge the address, then receive the first data byte without
+    /// acknowledging it, e.g. to reject a command byte. The controller sees the
+    /// write fail with a data NAK.
+    ///
+    /// A write without any data has no byte to reject, and is completed as
+    /// usual. To look at the byte before rejecting it, call `handle_part` with
+    /// a single byte buffer and drop the returned handler instead.
+    async fn reject_first_byte(self) -> Result<(), Self::Error> {
+        match self.handle_part(&mut [0]).await? {
+            // Dropping does not acknowledge the pending byte
+            WriteResult::Partial(handler) => drop(handler),
+            WriteResult::Complete(_) => {}
+        }
+        Ok(())
+    }
+
+    /// End the write transaction without accepting any further bytes, like
+    /// dropping the handler does: the pending last byte is not acknowledged,
+    /// or the address if no data was handled yet. Returns the number of bytes
+    /// received by this call, which is always 0, like `finish_with` on read
+    /// handlers returns the number of tail bytes.
+    ///
+    /// Unlike dropping, this lets implementations report errors while ending
+    /// the transaction. The default implementation drops the handler.
+    async fn finish(self) -> Result<usize, Self::Error> {
+        drop(self);
+        Ok(0)
+    }
 }
@@ -0,0 +1,168 @@
+//! Target wrapper modeling the time a target takes to process every byte
+
+use crate::{
+    AnyAddress, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction, ReadResult,
+    Transaction, WriteResult,
+};
+use core::slice;
+use embedded_hal_async::delay::DelayNs;
+
+/// Wraps an [`AsyncI2cTarget`] to wait `ns_per_byte` nanoseconds before every
+/// byte the handlers provide or receive.
+///
+/// This models a slow target, such as a microcontroller handling every byte
+/// in an interrupt, both in simulations using virtual time and on real
+/// hardware. Every `handle_part` call on the handlers is passed on one byte
+/// at a time, with a delay before each. Calls with an empty buffer are passed
+/// on as is.
+pub struct PacedTarget<T, D> {
+    inner: T,
+    delay: D,
+    ns_per_byte: u32,
+}
+
+impl<T, D: DelayNs> PacedTarget<T, D> {
+    /// Wrap `inner`, waiting with `delay`.
+    pub const fn new(inner: T, delay: D, ns_per_byte: u32) -> Self {
+        Self {
+            inner,
+            delay,
+            ns_per_byte,
+        }
+    }
+
+    /// Release the wrapped target and delay.
+    pub fn into_inner(self) -> (T, D) {
+        (self.inner, self.delay)
+    }
+}
+
+impl<T: AsyncI2cTarget, D: DelayNs> AsyncI2cTarget for PacedTarget<T, D> {
+    type Error = T::Error;
+    type Read<'a>
+        = PacedRead<'a, T::Read<'a>, D>
+    where
+        Self: 'a;
+    type Write<'a>
+        = PacedWrite<'a, T::Write<'a>, D>
+    where
+        Self: 'a;
+
+    async fn listen(
+        &mut self,
+    ) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        let delay = &mut self.delay;
+        let ns_per_byte = self.ns_per_byte;
+        Ok(match self.inner.listen().await? {
+            Transaction::Deselect => Transaction::Deselect,
+            Transaction::Read { address, handler } => Transaction::Read {
+                address,
+                handler: PacedRead {
+                    inner: handler,
+                    delay,
+                    ns_per_byte,
+                },
+            },
+            Transaction::Write { address, handler } => Transaction::Write {
+                address,
+                handler: PacedWrite {
+                    inner: handler,
+                    delay,
+                    ns_per_byte,
+                },
+            },
+        })
+    }
+
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        self.inner.addresses()
+    }
+
+    fn ops_remaining(&self) -> Option<usize> {
+        self.inner.ops_remaining()
+    }
+
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        self.inner.clock_range()
+    }
+
+    fn take_reset(&mut self) -> bool {
+        self.inner.take_reset()
+    }
+}
+
+/// Read handler for [`PacedTarget`]
+pub struct PacedRead<'a, R, D> {
+    inner: R,
+    delay: &'a mut D,
+    ns_per_byte: u32,
+}
+
+impl<R: AsyncReadTransaction, D: DelayNs> AsyncReadTransaction for PacedRead<'_, R, D> {
+    type Error = R::Error;
+
+    const MAX_OVERRUN: usize = R::MAX_OVERRUN;
+
+    async fn handle_part(self, buffer: &[u8]) -> Result<ReadResult<Self>, Self::Error> {
+        let Self {
+            mut inner,
+            delay,
+            ns_per_byte,
+        } = self;
+        if buffer.is_empty() {
+            inner = match inner.handle_part(buffer).await? {
+                ReadResult::Complete(size) => return Ok(ReadResult::Complete(size)),
+                ReadResult::Partial(inner) => inner,
+            };
+        }
+        for (i, byte) in buffer.iter().enumerate() {
+            delay.delay_ns(ns_per_byte).await;
+            inner = match inner.handle_part(slice::from_ref(byte)).await? {
+                ReadResult::Complete(size) => return Ok(ReadResult::Complete(i + size)),
+                ReadResult::Partial(inner) => inner,
+            };
+        }
+        Ok(ReadResult::Partial(Self {
+            inner,
+            delay,
+            ns_per_byte,
+        }))
+    }
+}
+
+/// Write handler for [`PacedTarget`]
+pub struct PacedWrite<'a, W, D> {
+    inner: W,
+    delay: &'a mut D,
+    ns_per_byte: u32,
+}
+
+impl<W: AsyncWriteTransaction, D: DelayNs> AsyncWriteTransaction for PacedWrite<'_, W, D> {
+    type Error = W::Error;
+
+    async fn handle_part(self, buffer: &mut [u8]) -> Result<WriteResult<Self>, Self::Error> {
+        let Self {
+            mut inner,
+            delay,
+            ns_per_byte,
+        } = self;
+        if buffer.is_empty() {
+            inner = match inner.handle_part(buffer).await? {
+                WriteResult::Complete(size) => return Ok(WriteResult::Complete(size)),
+                WriteResult::Partial(inner) => inner,
+            };
+        }
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            delay.delay_ns(ns_per_byte).await;
+            inner = match inner.handle_part(slice::from_mut(byte)).await? {
+                WriteResult::Complete(size) => return Ok(WriteResult::Complete(i + size)),
+                WriteResult::Partial(inner) => inner,
+            };
+        }
+        Ok(WriteResult::Partial(Self {
+            inner,
+            delay,
+            ns_per_byte,
+        }))
+    }
+}
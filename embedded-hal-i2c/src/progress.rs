@@ -0,0 +1,76 @@
+//! Controller wrapper counting the bytes transferred
+
+use crate::{AddressMode, AsyncI2cController, ErrorType, Operation, SyncI2cController};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Wraps a controller to add the number of bytes transferred to a shared
+/// counter, e.g. to show the progress of a long transfer.
+///
+/// The granularity is per transaction, not per operation: the operations are
+/// passed to the wrapped controller together, so they stay a single
+/// transaction with repeated starts on the bus, and the counter is only
+/// updated once that transaction completes successfully, with the bytes of
+/// all its operations. To follow the progress within a single large transfer,
+/// split it into several transactions.
+pub struct ProgressController<C> {
+    inner: C,
+    progress: Arc<AtomicUsize>,
+}
+
+impl<C> ProgressController<C> {
+    /// Count the bytes transferred by `inner` in `progress`.
+    pub const fn new(inner: C, progress: Arc<AtomicUsize>) -> Self {
+        Self { inner, progress }
+    }
+
+    /// The shared counter of bytes transferred.
+    pub fn progress(&self) -> &Arc<AtomicUsize> {
+        &self.progress
+    }
+
+    /// Release the wrapped controller.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn record<E>(&self, result: Result<(), E>, operations: &[Operation<'_>]) -> Result<(), E> {
+        if result.is_ok() {
+            let bytes = operations
+                .iter()
+                .map(|op| match op {
+                    Operation::Read(buf) => buf.len(),
+                    Operation::Write(buf) => buf.len(),
+                })
+                .sum();
+            self.progress.fetch_add(bytes, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+impl<C: ErrorType> ErrorType for ProgressController<C> {
+    type Error = C::Error;
+}
+
+impl<A: AddressMode, C: AsyncI2cController<A>> AsyncI2cController<A> for ProgressController<C> {
+    async fn transaction(
+        &mut self,
+        address: A,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.transaction(address, operations).await;
+        self.record(result, operations)
+    }
+}
+
+impl<A: AddressMode, C: SyncI2cController<A>> SyncI2cController<A> for ProgressController<C> {
+    fn transaction(
+        &mut self,
+        address: A,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.transaction(address, operations);
+        self.record(result, operations)
+    }
+}
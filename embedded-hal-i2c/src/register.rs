@@ -0,0 +1,131 @@
+//! Helpers for targets exposing a register map
+
+use crate::{AnyAddress, AsyncI2cTarget, AsyncWriteTransaction, Transaction, WriteResult};
+
+/// Number of bytes used to encode a register address on the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterAddressWidth {
+    /// Single byte register addresses, for up to 256 registers.
+    OneByte,
+    /// Two byte (little-endian) register addresses, for larger register maps.
+    TwoBytes,
+}
+
+impl RegisterAddressWidth {
+    /// Number of bytes in a register address.
+    pub const fn size(self) -> usize {
+        match self {
+            Self::OneByte => 1,
+            Self::TwoBytes => 2,
+        }
+    }
+
+    /// Decode a register address as received on the bus.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is not exactly [`Self::size`] bytes long.
+    pub fn parse(self, bytes: &[u8]) -> u16 {
+        match (self, bytes) {
+            (Self::OneByte, [addr]) => (*addr).into(),
+            (Self::TwoBytes, [lo, hi]) => u16::from_le_bytes([*lo, *hi]),
+            _ => unreachable!("register address must be exactly {} bytes", self.size()),
+        }
+    }
+}
+
+/// Register access received by [`RegisterAccessListener::listen`]
+#[must_use = "Implicitly dropping a Transaction will NAK the request"]
+pub enum RegisterAccess<R, W> {
+    /// The register address was written, immediately followed by a read
+    /// without a stop in between (the combined format).
+    Read {
+        /// The register being read
+        register: u16,
+        /// Handler to provide the contents of the register
+        handler: R,
+    },
+    /// The register address was written together with `size` bytes of data,
+    /// which have been put in the buffer.
+    Write {
+        /// The register being written
+        register: u16,
+        /// Number of data bytes following the register address
+        size: usize,
+    },
+    /// Only the register address was written. If a read follows without a
+    /// stop in between, it is reported as [`RegisterAccess::Read`] by the next
+    /// listen.
+    Pointer {
+        /// The register address written
+        register: u16,
+    },
+    /// Any other transaction, passed on as is.
+    ///
+    /// Writes too short to hold a register address are reported as
+    /// [`Transaction::Deselect`], as they have already ended.
+    Other(Transaction<R, W>),
+}
+
+/// Listens for the usual register access patterns on an [`AsyncI2cTarget`]
+///
+/// A register is written by writing its address followed by the data. It is
+/// read by writing its address, then reading after a repeated start. This
+/// keeps track of the register address in between, as both parts of a read are
+/// reported separately by the target.
+pub struct RegisterAccessListener {
+    address: AnyAddress,
+    width: RegisterAddressWidth,
+    pending: Option<u16>,
+}
+
+impl RegisterAccessListener {
+    /// Recognize register accesses to `address`, using register addresses of
+    /// `width` bytes.
+    pub const fn new(address: AnyAddress, width: RegisterAddressWidth) -> Self {
+        Self {
+            address,
+            width,
+            pending: None,
+        }
+    }
+
+    /// Listen for the next transaction on `target`, receiving the data of a
+    /// register write into `buffer`. Any data beyond the size of `buffer` is
+    /// not acknowledged.
+    pub async fn listen<'a, T: AsyncI2cTarget>(
+        &mut self,
+        target: &'a mut T,
+        buffer: &mut [u8],
+    ) -> Result<RegisterAccess<T::Read<'a>, T::Write<'a>>, T::Error> {
+        let pending = self.pending.take();
+        match target.listen().await? {
+            Transaction::Read { address, handler } if address == self.address => {
+                Ok(match pending {
+                    Some(register) => RegisterAccess::Read { register, handler },
+                    None => RegisterAccess::Other(Transaction::Read { address, handler }),
+                })
+            }
+            Transaction::Write { address, handler } if address == self.address => {
+                let mut pointer = [0; 2];
+                let pointer = &mut pointer[..self.width.size()];
+                let size = match handler.handle_part(pointer).await? {
+                    WriteResult::Complete(size) if size < pointer.len() => {
+                        return Ok(RegisterAccess::Other(Transaction::Deselect));
+                    }
+                    WriteResult::Complete(_) => 0,
+                    WriteResult::Partial(handler) => handler.handle_complete(buffer).await?,
+                };
+
+                let register = self.width.parse(pointer);
+                if size == 0 {
+                    self.pending = Some(register);
+                    Ok(RegisterAccess::Pointer { register })
+                } else {
+                    Ok(RegisterAccess::Write { register, size })
+                }
+            }
+            other => Ok(RegisterAccess::Other(other)),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Retrying transient errors of the storage behind a handler
+
+use crate::{AsyncReadTransaction, AsyncWriteTransaction};
+
+/// Error returned by [`RetryHandler`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryError<H, S> {
+    /// Error of the wrapped handler
+    Handler(H),
+    /// Error of the storage, which was either not transient or persisted for
+    /// all attempts
+    Storage(S),
+}
+
+/// Wraps a read or write handler to retry transient errors of the storage
+/// behind it
+///
+/// The storage is accessed up to `attempts` times, for as long as
+/// `is_transient` classifies its errors as transient. Errors of the handler
+/// itself are never retried: the bytes it transferred cannot be taken back,
+/// so it is consumed by the error.
+///
+/// For reads, the data is fetched before the address is acknowledged, so if
+/// all attempts fail the read is not acknowledged at all. For writes, the data
+/// is received before it is stored, so a failure to store it can no longer be
+/// signaled to the controller and is only returned.
+pub struct RetryHandler<H, F> {
+    handler: H,
+    attempts: usize,
+    is_transient: F,
+}
+
+impl<H, F> RetryHandler<H, F> {
+    /// Wrap `handler`, accessing the storage at most `attempts` times.
+    pub const fn new(handler: H, attempts: usize, is_transient: F) -> Self {
+        Self {
+            handler,
+            attempts,
+            is_transient,
+        }
+    }
+}
+
+impl<R: AsyncReadTransaction, F> RetryHandler<R, F> {
+    /// Fill `buffer` using `fetch`, which returns the number of bytes
+    /// fetched, and provide those bytes to the controller. Reads past the end
+    /// get the overrun character `ovc`.
+    ///
+    /// Returns the number of bytes read by the controller, including overrun.
+    pub async fn read_from<E>(
+        self,
+        buffer: &mut [u8],
+        mut fetch: impl AsyncFnMut(&mut [u8]) -> Result<usize, E>,
+        ovc: u8,
+    ) -> Result<usize, RetryError<R::Error, E>>
+    where
+        F: FnMut(&E) -> bool,
+    {
+        let Self {
+            handler,
+            attempts,
+            mut is_transient,
+        } = self;
+        let mut attempt = 1;
+        let len = loop {
+            match fetch(buffer).await {
+                Ok(len) => break len,
+                Err(e) if attempt < attempts && is_transient(&e) => attempt += 1,
+                Err(e) => return Err(RetryError::Storage(e)),
+            }
+        };
+        handler
+            .handle_complete(&buffer[..len], ovc)
+            .await
+            .map_err(RetryError::Handler)
+    }
+}
+
+impl<W: AsyncWriteTransaction, F> RetryHandler<W, F> {
+    /// Receive the write into `buffer`, then pass the received bytes to
+    /// `store`.
+    ///
+    /// Returns the number of bytes received.
+    pub async fn write_to<E>(
+        self,
+        buffer: &mut [u8],
+        mut store: impl AsyncFnMut(&[u8]) -> Result<(), E>,
+    ) -> Result<usize, RetryError<W::Error, E>>
+    where
+        F: FnMut(&E) -> bool,
+    {
+        let Self {
+            handler,
+            attempts,
+            mut is_transient,
+        } = self;
+        let len = handler
+            .handle_complete(buffer)
+            .await
+            .map_err(RetryError::Handler)?;
+        let mut attempt = 1;
+        loop {
+            match store(&buffer[..len]).await {
+                Ok(()) => return Ok(len),
+                Err(e) if attempt < attempts && is_transient(&e) => attempt += 1,
+                Err(e) => return Err(RetryError::Storage(e)),
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Target wrapper for code that only handles 7-bit addresses
+
+use crate::{AnyAddress, AsyncI2cTarget, Transaction};
+
+/// Wraps an [`AsyncI2cTarget`] such that only transactions for 7-bit
+/// addresses reach the application.
+///
+/// This allows using target code written with only [`AnyAddress::Seven`] in
+/// mind, e.g. code ported from an API using `SevenBitAddress`, on a target
+/// that also receives 10-bit addresses. Transactions for 10-bit addresses are
+/// not acknowledged, so the controller gets
+/// [`ErrorKind::NoAcknowledge`](crate::ErrorKind::NoAcknowledge) for the
+/// address, and `listen` reports them as [`Transaction::Deselect`].
+pub struct SevenBitTarget<T> {
+    inner: T,
+}
+
+impl<T> SevenBitTarget<T> {
+    /// Only pass the 7-bit addressed transactions of `inner` on.
+    pub const fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Release the wrapped target.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsyncI2cTarget> AsyncI2cTarget for SevenBitTarget<T> {
+    type Error = T::Error;
+    type Read<'a>
+        = T::Read<'a>
+    where
+        Self: 'a;
+    type Write<'a>
+        = T::Write<'a>
+    where
+        Self: 'a;
+
+    async fn listen(
+        &mut self,
+    ) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        Ok(match self.inner.listen().await? {
+            // Dropping the handler does not acknowledge the address
+            Transaction::Read {
+                address: AnyAddress::Ten(_),
+                handler,
+            } => {
+                drop(handler);
+                Transaction::Deselect
+            }
+            Transaction::Write {
+                address: AnyAddress::Ten(_),
+                handler,
+            } => {
+                drop(handler);
+                Transaction::Deselect
+            }
+            transaction => transaction,
+        })
+    }
+
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        self.inner
+            .addresses()
+            .filter(|address| matches!(address, AnyAddress::Seven(_)))
+    }
+
+    fn ops_remaining(&self) -> Option<usize> {
+        self.inner.ops_remaining()
+    }
+
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        self.inner.clock_range()
+    }
+
+    fn take_reset(&mut self) -> bool {
+        self.inner.take_reset()
+    }
+}
@@ -0,0 +1,197 @@
+//! SMBus compliant target wrapper
+
+use crate::{
+    AnyAddress, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction, ReadResult,
+    Transaction, WriteResult,
+};
+use core::future::{Future, poll_fn};
+use core::pin::pin;
+use core::task::Poll;
+use embedded_hal_async::delay::DelayNs;
+
+/// Minimum time after which an SMBus device must release the bus, in
+/// microseconds.
+pub const SMBUS_TIMEOUT_US: u32 = 25_000;
+
+/// Error returned by [`SmbusTarget`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmbusError<E> {
+    /// Error of the wrapped target
+    Inner(E),
+    /// Handling the transaction took too long. The transaction has been
+    /// aborted and the bus released.
+    Timeout,
+}
+
+/// Wraps an [`AsyncI2cTarget`] to release the bus when handling a transaction
+/// takes too long, as required by SMBus.
+///
+/// Every call to `handle_part` on the handlers must finish within
+/// `timeout_us` microseconds per byte of the buffer provided (at least one).
+/// If it does not, the handler is dropped, which releases the bus by not
+/// acknowledging the write or finishing the read with the overrun character,
+/// and [`SmbusError::Timeout`] is returned.
+pub struct SmbusTarget<T, D> {
+    inner: T,
+    delay: D,
+    timeout_us: u32,
+}
+
+impl<T, D: DelayNs> SmbusTarget<T, D> {
+    /// Wrap `inner`, measuring time with `delay`.
+    pub const fn new(inner: T, delay: D, timeout_us: u32) -> Self {
+        Self {
+            inner,
+            delay,
+            timeout_us,
+        }
+    }
+
+    /// Release the wrapped target and delay.
+    pub fn into_inner(self) -> (T, D) {
+        (self.inner, self.delay)
+    }
+}
+
+/// Run `future`, giving up once `timeout_us` have passed.
+pub(crate) async fn with_timeout<F: Future>(
+    delay: &mut impl DelayNs,
+    timeout_us: u32,
+    future: F,
+) -> Option<F::Output> {
+    let mut future = pin!(future);
+    let mut timeout = pin!(delay.delay_us(timeout_us));
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            Poll::Ready(Some(output))
+        } else if timeout.as_mut().poll(cx).is_ready() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+fn budget(timeout_us: u32, len: usize) -> u32 {
+    let bytes = u32::try_from(len.max(1)).unwrap_or(u32::MAX);
+    timeout_us.saturating_mul(bytes)
+}
+
+impl<T: AsyncI2cTarget, D: DelayNs> AsyncI2cTarget for SmbusTarget<T, D> {
+    type Error = SmbusError<T::Error>;
+    type Read<'a>
+        = SmbusRead<'a, T::Read<'a>, D>
+    where
+        Self: 'a;
+    type Write<'a>
+        = SmbusWrite<'a, T::Write<'a>, D>
+    where
+        Self: 'a;
+
+    async fn listen(
+        &mut self,
+    ) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        let delay = &mut self.delay;
+        let timeout_us = self.timeout_us;
+        Ok(
+            match self.inner.listen().await.map_err(SmbusError::Inner)? {
+                Transaction::Deselect => Transaction::Deselect,
+                Transaction::Read { address, handler } => Transaction::Read {
+                    address,
+                    handler: SmbusRead {
+                        inner: handler,
+                        delay,
+                        timeout_us,
+                    },
+                },
+                Transaction::Write { address, handler } => Transaction::Write {
+                    address,
+                    handler: SmbusWrite {
+                        inner: handler,
+                        delay,
+                        timeout_us,
+                    },
+                },
+            },
+        )
+    }
+
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        self.inner.addresses()
+    }
+
+    fn ops_remaining(&self) -> Option<usize> {
+        self.inner.ops_remaining()
+    }
+
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        self.inner.clock_range()
+    }
+
+    fn take_reset(&mut self) -> bool {
+        self.inner.take_reset()
+    }
+}
+
+/// Read handler for [`SmbusTarget`]
+pub struct SmbusRead<'a, R, D> {
+    inner: R,
+    delay: &'a mut D,
+    timeout_us: u32,
+}
+
+impl<R: AsyncReadTransaction, D: DelayNs> AsyncReadTransaction for SmbusRead<'_, R, D> {
+    type Error = SmbusError<R::Error>;
+
+    const MAX_OVERRUN: usize = R::MAX_OVERRUN;
+
+    async fn handle_part(self, buffer: &[u8]) -> Result<ReadResult<Self>, Self::Error> {
+        let Self {
+            inner,
+            delay,
+            timeout_us,
+        } = self;
+        let budget = budget(timeout_us, buffer.len());
+        match with_timeout(&mut *delay, budget, inner.handle_part(buffer)).await {
+            None => Err(SmbusError::Timeout),
+            Some(Err(e)) => Err(SmbusError::Inner(e)),
+            Some(Ok(ReadResult::Complete(size))) => Ok(ReadResult::Complete(size)),
+            Some(Ok(ReadResult::Partial(inner))) => Ok(ReadResult::Partial(Self {
+                inner,
+                delay,
+                timeout_us,
+            })),
+        }
+    }
+}
+
+/// Write handler for [`SmbusTarget`]
+pub struct SmbusWrite<'a, W, D> {
+    inner: W,
+    delay: &'a mut D,
+    timeout_us: u32,
+}
+
+impl<W: AsyncWriteTransaction, D: DelayNs> AsyncWriteTransaction for SmbusWrite<'_, W, D> {
+    type Error = SmbusError<W::Error>;
+
+    async fn handle_part(self, buffer: &mut [u8]) -> Result<WriteResult<Self>, Self::Error> {
+        let Self {
+            inner,
+            delay,
+            timeout_us,
+        } = self;
+        let budget = budget(timeout_us, buffer.len());
+        match with_timeout(&mut *delay, budget, inner.handle_part(buffer)).await {
+            None => Err(SmbusError::Timeout),
+            Some(Err(e)) => Err(SmbusError::Inner(e)),
+            Some(Ok(WriteResult::Complete(size))) => Ok(WriteResult::Complete(size)),
+            Some(Ok(WriteResult::Partial(inner))) => Ok(WriteResult::Partial(Self {
+                inner,
+                delay,
+                timeout_us,
+            })),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Register map target backed by a plain struct
+
+use crate::register::{RegisterAccess, RegisterAccessListener, RegisterAddressWidth};
+use crate::{AnyAddress, AsyncI2cTarget, AsyncReadTransaction};
+use bytemuck::{Pod, bytes_of, bytes_of_mut};
+
+/// Register map of a device at a single address, stored as the bytes of a
+/// [`Pod`] struct
+///
+/// Every byte of the struct is a register, with the register address being
+/// its offset. Register writes land directly in the fields of the struct, and
+/// register reads return its bytes, auto-incrementing through the struct.
+/// This makes a `#[repr(C)]` struct with the layout of the register map a
+/// typed view of it.
+///
+/// Writes beyond the end of the struct are acknowledged but ignored. Reads
+/// beyond the end are answered with `0xFF`. Transactions not following the
+/// register access patterns are not acknowledged.
+pub struct StructTarget<S, T> {
+    target: T,
+    listener: RegisterAccessListener,
+    value: S,
+}
+
+impl<S: Pod, T: AsyncI2cTarget> StructTarget<S, T> {
+    /// Serve the bytes of `value` as registers of the device at `address` on
+    /// `target`, using one byte register addresses.
+    pub const fn new(target: T, address: AnyAddress, value: S) -> Self {
+        Self {
+            target,
+            listener: RegisterAccessListener::new(address, RegisterAddressWidth::OneByte),
+            value,
+        }
+    }
+
+    /// The current contents of the registers.
+    pub const fn value(&self) -> &S {
+        &self.value
+    }
+
+    /// The contents of the registers, to update them as the device would.
+    pub const fn value_mut(&mut self) -> &mut S {
+        &mut self.value
+    }
+
+    /// Handle the next transaction, receiving register writes into `buffer`.
+    pub async fn handle(&mut self, buffer: &mut [u8]) -> Result<(), T::Error> {
+        match self.listener.listen(&mut self.target, buffer).await? {
+            RegisterAccess::Write { register, size } => {
+                let registers = bytes_of_mut(&mut self.value);
+                let start = usize::from(register).min(registers.len());
+                let len = size.min(registers.len() - start);
+                registers[start..start + len].copy_from_slice(&buffer[..len]);
+            }
+            RegisterAccess::Read { register, handler } => {
+                let registers = bytes_of(&self.value);
+                let start = usize::from(register).min(registers.len());
+                handler.handle_complete(&registers[start..], 0xFF).await?;
+            }
+            RegisterAccess::Pointer { .. } | RegisterAccess::Other(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Release the wrapped target and the register contents.
+    pub fn into_inner(self) -> (T, S) {
+        (self.target, self.value)
+    }
+}
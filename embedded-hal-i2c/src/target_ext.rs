@@ -0,0 +1,82 @@
+//! Convenience functions for targets
+
+use crate::smbus::with_timeout;
+use crate::{AnyAddress, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction, Transaction};
+use embedded_hal_async::delay::DelayNs;
+
+/// Serves the operations of a transaction handled with
+/// [`AsyncI2cTargetExt::handle_transaction`]
+pub trait OperationProvider {
+    /// Process the data received by a write operation to `address`.
+    fn write(&mut self, address: AnyAddress, data: &[u8]);
+
+    /// The data to send for a read operation from `address`.
+    fn read(&mut self, address: AnyAddress) -> &[u8];
+}
+
+/// Extension trait with common operations built on [`AsyncI2cTarget::listen`]
+///
+/// This is implemented for every [`AsyncI2cTarget`].
+pub trait AsyncI2cTargetExt: AsyncI2cTarget {
+    /// Not acknowledge every transaction until none arrived for `timeout_us`
+    /// microseconds, e.g. to quiet the bus during teardown. Returns the number
+    /// of transactions refused.
+    ///
+    /// Giving up on `listen` once the timeout expires requires it to be
+    /// cancel safe.
+    async fn drain(
+        &mut self,
+        delay: &mut impl DelayNs,
+        timeout_us: u32,
+    ) -> Result<usize, Self::Error> {
+        let mut refused = 0;
+        loop {
+            match with_timeout(&mut *delay, timeout_us, self.listen()).await {
+                None => return Ok(refused),
+                Some(Err(e)) => return Err(e),
+                Some(Ok(Transaction::Deselect)) => {}
+                // Dropping a handler does not acknowledge the address
+                Some(Ok(Transaction::Read { handler, .. })) => {
+                    drop(handler);
+                    refused += 1;
+                }
+                Some(Ok(Transaction::Write { handler, .. })) => {
+                    drop(handler);
+                    refused += 1;
+                }
+            }
+        }
+    }
+
+    /// Handle all operations of the next transaction with `provider`,
+    /// returning the number of operations once the transaction ends.
+    ///
+    /// Writes are received into `buffer`, of which any overrun is not
+    /// acknowledged. Reads beyond the data from the provider are answered
+    /// with `ovc`. Deselects before the first operation are skipped.
+    async fn handle_transaction(
+        &mut self,
+        provider: &mut impl OperationProvider,
+        buffer: &mut [u8],
+        ovc: u8,
+    ) -> Result<usize, Self::Error> {
+        let mut operations = 0;
+        loop {
+            match self.listen().await? {
+                Transaction::Deselect if operations > 0 => return Ok(operations),
+                Transaction::Deselect => {}
+                Transaction::Read { address, handler } => {
+                    handler.handle_complete(provider.read(address), ovc).await?;
+                    operations += 1;
+                }
+                Transaction::Write { address, handler } => {
+                    let size = handler.handle_complete(buffer).await?;
+                    provider.write(address, &buffer[..size]);
+                    operations += 1;
+                }
+            }
+        }
+    }
+}
+
+impl<T: AsyncI2cTarget + ?Sized> AsyncI2cTargetExt for T {}
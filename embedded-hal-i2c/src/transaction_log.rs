@@ -0,0 +1,120 @@
+//! Allocation-free log of the most recent transactions
+
+use crate::{AnyAddress, AsyncI2cTarget, Transaction, TransactionTag};
+use heapless::Deque;
+
+/// Ring log of the last `N` transaction tags
+///
+/// Once full, recording a new tag evicts the oldest one.
+pub struct TransactionLog<const N: usize> {
+    entries: Deque<TransactionTag, N>,
+}
+
+impl<const N: usize> TransactionLog<N> {
+    /// Create an empty log.
+    pub const fn new() -> Self {
+        Self {
+            entries: Deque::new(),
+        }
+    }
+
+    /// Record `tag`, evicting the oldest entry if the log is full.
+    pub fn record(&mut self, tag: TransactionTag) {
+        if self.entries.is_full() {
+            self.entries.pop_front();
+        }
+        // Cannot fail, as there is room now.
+        let _ = self.entries.push_back(tag);
+    }
+
+    /// The recorded tags, from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = &TransactionTag> {
+        self.entries.iter()
+    }
+
+    /// Number of recorded tags.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether nothing has been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all recorded tags.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl<const N: usize> Default for TransactionLog<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wraps an [`AsyncI2cTarget`] to record every transaction it reports in a
+/// [`TransactionLog`].
+///
+/// The handlers of the wrapped target are passed on unchanged.
+pub struct LoggingTarget<T, const N: usize> {
+    inner: T,
+    log: TransactionLog<N>,
+}
+
+impl<T, const N: usize> LoggingTarget<T, N> {
+    /// Record the transactions of `inner`, starting with an empty log.
+    pub const fn new(inner: T) -> Self {
+        Self {
+            inner,
+            log: TransactionLog::new(),
+        }
+    }
+
+    /// The transactions recorded so far.
+    pub const fn log(&self) -> &TransactionLog<N> {
+        &self.log
+    }
+
+    /// Release the wrapped target and the log.
+    pub fn into_inner(self) -> (T, TransactionLog<N>) {
+        (self.inner, self.log)
+    }
+}
+
+impl<T: AsyncI2cTarget, const N: usize> AsyncI2cTarget for LoggingTarget<T, N> {
+    type Error = T::Error;
+    type Read<'a>
+        = T::Read<'a>
+    where
+        Self: 'a;
+    type Write<'a>
+        = T::Write<'a>
+    where
+        Self: 'a;
+
+    async fn listen(
+        &mut self,
+    ) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        let transaction = self.inner.listen().await?;
+        self.log.record(transaction.tag());
+        Ok(transaction)
+    }
+
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        self.inner.addresses()
+    }
+
+    fn ops_remaining(&self) -> Option<usize> {
+        self.inner.ops_remaining()
+    }
+
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        self.inner.clock_range()
+    }
+
+    fn take_reset(&mut self) -> bool {
+        self.inner.take_reset()
+    }
+}
@@ -1,22 +1,37 @@
-use embedded_hal_i2c::{AsyncI2cTarget, TransactionExpectWrite};
+use embedded_hal_i2c::fixed_address::FixedAddressTarget;
+use embedded_hal_i2c::{AnyAddress, AsyncI2cTarget, TransactionExpectWrite};
 use std::sync::atomic::{AtomicBool, Ordering};
 
+pub use embedded_hal_i2c::register::RegisterAddressWidth;
+
 pub mod tests;
 
 pub trait Interface {
     type Error;
 
-    fn read_reg<'buf>(&mut self, addr: u8, buf: &'buf mut [u8]) -> Result<&'buf [u8], Self::Error>;
-    fn write_reg(&mut self, addr: u8, data: &[u8]) -> Result<(), Self::Error>;
+    fn read_reg<'buf>(&mut self, addr: u16, buf: &'buf mut [u8])
+    -> Result<&'buf [u8], Self::Error>;
+    fn write_reg(&mut self, addr: u16, data: &[u8]) -> Result<(), Self::Error>;
 }
 
-pub async fn run(mut i2c: impl AsyncI2cTarget, mut interface: impl Interface, stop: &AtomicBool) {
-    let my_address = 0x2a_u8.into();
+/// Configure `i2c` to respond to `my_address` only, as done by [`run`]. The
+/// address is reported by [`AsyncI2cTarget::addresses`].
+pub fn expander_target<I: AsyncI2cTarget>(i2c: I, my_address: AnyAddress) -> FixedAddressTarget<I> {
+    FixedAddressTarget::new(i2c, my_address)
+}
 
+pub async fn run(
+    i2c: impl AsyncI2cTarget,
+    my_address: AnyAddress,
+    width: RegisterAddressWidth,
+    mut interface: impl Interface,
+    stop: &AtomicBool,
+) {
+    let mut i2c = expander_target(i2c, my_address);
     let mut buf = [0u8; 64];
     while !stop.load(Ordering::Relaxed) {
-        // We need to start with a write. This will either be a single byte (for a "write then read"),
-        // or a multi-byte sequence (for a "write then write")
+        // We need to start with a write. This will either be just the register address (for a
+        // "write then read"), or a longer sequence (for a "write then write")
         let res = i2c.listen_expect_write(my_address, &mut buf).await;
         let Ok(TransactionExpectWrite::ExpectedCompleteWrite { size }) = res else {
             // I dunno what they wanted.
@@ -24,23 +39,22 @@ pub async fn run(mut i2c: impl AsyncI2cTarget, mut interface: impl Interface, st
         };
         drop(res);
 
-        let used = &buf[..size];
-        match used {
-            [] => {
-                // why do you send me this empty write transaction
-                continue;
-            }
-            [reg_addr] => {
-                // We were written just an address, prep for a switch to a read
-                if let Ok(data) = interface.read_reg(*reg_addr, &mut buf) {
-                    // we don't really care if they gave up, if this is complete, then great,
-                    // if not, we'll drop the handler
-                    let _ = i2c.listen_expect_read(my_address, data).await;
-                }
-            }
-            [reg_addr, data @ ..] => {
-                let _ = interface.write_reg(*reg_addr, data);
+        if size < width.size() {
+            // why do you send me this too short write transaction
+            continue;
+        }
+
+        let (reg_addr, data) = buf[..size].split_at(width.size());
+        let reg_addr = width.parse(reg_addr);
+        if data.is_empty() {
+            // We were written just an address, prep for a switch to a read
+            if let Ok(data) = interface.read_reg(reg_addr, &mut buf) {
+                // we don't really care if they gave up, if this is complete, then great,
+                // if not, we'll drop the handler
+                let _ = i2c.listen_expect_read(my_address, data).await;
             }
+        } else {
+            let _ = interface.write_reg(reg_addr, data);
         }
     }
 }
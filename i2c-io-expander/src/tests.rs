@@ -1,48 +1,62 @@
-use crate::Interface;
-use embedded_hal_i2c::AsyncI2cTarget;
+use crate::{Interface, RegisterAddressWidth};
+use embedded_hal_i2c::{AnyAddress, AsyncI2cTarget};
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 
 struct TestInterface {
-    data: [u32; 32],
+    data: Vec<u32>,
 }
 
 impl Interface for TestInterface {
     type Error = ();
 
-    fn read_reg<'buf>(&mut self, addr: u8, buf: &'buf mut [u8]) -> Result<&'buf [u8], Self::Error> {
+    fn read_reg<'buf>(
+        &mut self,
+        addr: u16,
+        buf: &'buf mut [u8],
+    ) -> Result<&'buf [u8], Self::Error> {
         if buf.len() < 4 {
             return Err(());
         }
 
-        let data = self.data[usize::from(addr)];
+        let data = *self.data.get(usize::from(addr)).ok_or(())?;
         buf[..4].copy_from_slice(&data.to_le_bytes());
 
         Ok(&buf[..4])
     }
 
-    fn write_reg(&mut self, addr: u8, data: &[u8]) -> Result<(), Self::Error> {
+    fn write_reg(&mut self, addr: u16, data: &[u8]) -> Result<(), Self::Error> {
         let Ok(data) = data.try_into() else {
             return Err(());
         };
 
         let word = u32::from_le_bytes(data);
-        self.data[usize::from(addr)] = word;
+        *self.data.get_mut(usize::from(addr)).ok_or(())? = word;
 
         Ok(())
     }
 }
 
+pub const ADDRESS: AnyAddress = AnyAddress::Seven(0x2a);
+
 pub async fn server(i2c: impl AsyncI2cTarget, stop: Arc<AtomicBool>) {
-    let iface = TestInterface { data: [0; 32] };
-    super::run(i2c, iface, &stop).await;
+    let iface = TestInterface { data: vec![0; 32] };
+    super::run(i2c, ADDRESS, RegisterAddressWidth::OneByte, iface, &stop).await;
+}
+
+pub async fn server_16bit(i2c: impl AsyncI2cTarget, stop: Arc<AtomicBool>) {
+    let iface = TestInterface {
+        data: vec![0; 0x400],
+    };
+    super::run(i2c, ADDRESS, RegisterAddressWidth::TwoBytes, iface, &stop).await;
 }
 
 // TODO: Make this runnable with real devices
 #[cfg(test)]
 mod test_locally {
     use super::*;
-    use embedded_hal_i2c::AsyncI2cController;
+    use embedded_hal_i2c::controller_ext::AsyncI2cControllerExt;
+    use embedded_hal_i2c::{AsyncI2cController, ErrorKind, NoAcknowledgeSource};
     use std::sync::atomic::Ordering;
     use tokio::join;
 
@@ -123,4 +137,117 @@ mod test_locally {
 
         join!(server_fut, client_fut);
     }
+
+    #[tokio::test]
+    async fn works_locally_16bit() {
+        let (mut cont, target) = simulator::simulator();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let server_fut = server_16bit(target, Arc::clone(&stop));
+
+        let client_fut = async move {
+            for i in 0x3f0..0x400_u16 {
+                let [lo, hi] = i.to_le_bytes();
+                let buf = [lo, hi, lo, hi, 0, 0];
+                cont.write(A7, &buf).await.unwrap();
+            }
+
+            for i in 0x3f0..0x400_u16 {
+                let mut buf = [0xFF; 4];
+                cont.write_read(A7, &i.to_le_bytes(), &mut buf)
+                    .await
+                    .unwrap();
+
+                assert_eq!(u32::from_le_bytes(buf), u32::from(i));
+            }
+
+            // Only the high byte differs, so this must not alias register 0x3f0
+            let mut buf = [0xFF; 4];
+            cont.write_read(A7, &0xf0_u16.to_le_bytes(), &mut buf)
+                .await
+                .unwrap();
+            assert_eq!(buf, [0; 4]);
+
+            stop.store(true, Ordering::Relaxed);
+        };
+
+        join!(server_fut, client_fut);
+    }
+
+    #[test]
+    fn target_reports_address() {
+        let (_, target) = simulator::simulator();
+        assert_eq!(target.addresses().count(), 0);
+        let target = crate::expander_target(target, ADDRESS);
+        assert!(target.addresses().eq([AnyAddress::Seven(0x2a)]));
+    }
+
+    #[tokio::test]
+    async fn other_addresses_are_nacked() {
+        let (mut cont, target) = simulator::simulator();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let server_fut = server(target, Arc::clone(&stop));
+
+        let client_fut = async move {
+            let mut buf = [0xFF; 4];
+            let err = cont.write_read(A7 + 1, &[0], &mut buf).await.unwrap_err();
+            assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+
+            cont.write_read(A7, &[0], &mut buf).await.unwrap();
+            assert_eq!(buf, [0; 4]);
+
+            stop.store(true, Ordering::Relaxed);
+        };
+
+        join!(server_fut, client_fut);
+    }
+
+    /// Byte registers, where reading continues into the following registers
+    struct AutoIncrement {
+        data: [u8; 16],
+    }
+
+    impl Interface for AutoIncrement {
+        type Error = ();
+
+        fn read_reg<'buf>(
+            &mut self,
+            addr: u16,
+            buf: &'buf mut [u8],
+        ) -> Result<&'buf [u8], Self::Error> {
+            let data = self.data.get(usize::from(addr)..).ok_or(())?;
+            let len = data.len().min(buf.len());
+            buf[..len].copy_from_slice(&data[..len]);
+            Ok(&buf[..len])
+        }
+
+        fn write_reg(&mut self, _addr: u16, _data: &[u8]) -> Result<(), Self::Error> {
+            Err(())
+        }
+    }
+
+    #[tokio::test]
+    async fn dump_registers() {
+        let (mut cont, mut target) = simulator::simulator();
+        target.set_address(ADDRESS);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let iface = AutoIncrement {
+            data: core::array::from_fn(|i| i as u8 * 0x11),
+        };
+        let server_fut =
+            super::super::run(target, ADDRESS, RegisterAddressWidth::OneByte, iface, &stop);
+
+        let client_fut = async {
+            let mut buf = [0; 8];
+            let registers = cont.dump_registers(A7, 3, 4, &mut buf).await.unwrap();
+            assert_eq!(registers, [0x33, 0x44, 0x55, 0x66]);
+
+            stop.store(true, Ordering::Relaxed);
+            drop(cont);
+        };
+
+        join!(server_fut, client_fut);
+    }
 }
@@ -1,8 +1,10 @@
+use crate::Endianness;
 use embedded_hal_i2c::{AddressMode, AsyncI2cController, Error as _, ErrorKind};
 
 pub struct I2cRam<I, A> {
     i2c: I,
     address: A,
+    endianness: Endianness,
 }
 
 impl<I, A> I2cRam<I, A>
@@ -11,12 +13,23 @@ where
     A: AddressMode + Copy,
 {
     pub const fn new(i2c: I, address: A) -> I2cRam<I, A> {
-        I2cRam { i2c, address }
+        I2cRam {
+            i2c,
+            address,
+            endianness: Endianness::Little,
+        }
+    }
+
+    /// Send memory addresses in the byte order of `endianness`, which must
+    /// match the configuration of the target.
+    pub const fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
     }
 
     pub async fn read(&mut self, address: u16, buf: &mut [u8]) -> Result<(), Error<I::Error>> {
         self.i2c
-            .write_read(self.address, &address.to_le_bytes(), buf)
+            .write_read(self.address, &self.endianness.encode(address), buf)
             .await
             .map_err(|e| match e.kind() {
                 ErrorKind::NoAcknowledge(_) => Error::OutOfBounds,
@@ -37,7 +50,7 @@ where
             let transaction_len = data_len + ADDR_SIZE;
 
             let (addr_buf, data_buf) = chunk_buf.split_at_mut(ADDR_SIZE);
-            addr_buf.copy_from_slice(&chunk_address.to_le_bytes());
+            addr_buf.copy_from_slice(&self.endianness.encode(chunk_address));
             data_buf[..data_len].copy_from_slice(chunk);
 
             self.i2c
@@ -1,16 +1,171 @@
+use embedded_hal_i2c::fixed_address::FixedAddressTarget;
+use embedded_hal_i2c::length_limited::LengthLimitedTarget;
 use embedded_hal_i2c::{
     AnyAddress, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction,
     TransactionExpectEither, WriteResult,
 };
 use log::info;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 pub mod driver;
 
 pub const TARGET_ADDR: Option<AnyAddress> = Some(AnyAddress::Seven(0x20));
 const BUFLEN: usize = 512;
 
-pub async fn target_service<I: AsyncI2cTarget>(mut i2c: I, stop: &AtomicBool)
+/// What happens to the address pointer of the RAM when the controller ends a
+/// transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeselectBehavior {
+    /// Keep the address pointer, so a following read without address
+    /// continues where the last transaction ended. This is how I2C EEPROMs
+    /// implement their "current address read".
+    #[default]
+    RetainPointer,
+    /// Reset the address pointer to 0 on every STOP.
+    ResetPointer,
+}
+
+/// Byte order of the 2-byte memory address sent before the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endianness {
+    /// Least significant byte first.
+    #[default]
+    Little,
+    /// Most significant byte first, as used by most I2C EEPROMs.
+    Big,
+}
+
+impl Endianness {
+    /// The bytes to send for `address`.
+    pub const fn encode(self, address: u16) -> [u8; 2] {
+        match self {
+            Self::Little => address.to_le_bytes(),
+            Self::Big => address.to_be_bytes(),
+        }
+    }
+
+    /// The address sent as `bytes`.
+    pub const fn decode(self, bytes: [u8; 2]) -> u16 {
+        match self {
+            Self::Little => u16::from_le_bytes(bytes),
+            Self::Big => u16::from_be_bytes(bytes),
+        }
+    }
+}
+
+/// Configuration of the RAM served by [`target_service_with_config`]
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub deselect: DeselectBehavior,
+    /// Byte order of the memory address at the start of every write.
+    pub endianness: Endianness,
+    /// Duration of the internal write cycle started by a STOP after writing
+    /// data, like the tWR of an EEPROM. During the write cycle the address of
+    /// every transaction is not acknowledged.
+    pub write_cycle: Option<Duration>,
+    /// Source of the current time, used for timing the write cycle. This can
+    /// be replaced to run on virtual time in tests.
+    pub clock: fn() -> Instant,
+    /// Command byte for reading the address pointer. A write of just this
+    /// byte makes the next read return the current address pointer, in the
+    /// configured byte order, instead of the RAM contents.
+    pub pointer_query: Option<u8>,
+    /// Unlock sequence, as memory address and the bytes to write there. Until
+    /// the controller writes exactly this sequence, reads are not
+    /// acknowledged, and neither is the data of any other write.
+    pub unlock: Option<(u16, &'static [u8])>,
+    /// When set, receives the bytes served on the last read transaction,
+    /// including the overrun characters provided past the end of the RAM.
+    pub last_read: Option<Arc<Mutex<Vec<u8>>>>,
+    /// Most bytes a single transaction may transfer, including the memory
+    /// address, so one controller cannot keep the service busy. Writes are
+    /// not acknowledged beyond the budget, reads get the overrun character.
+    pub byte_budget: Option<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            deselect: DeselectBehavior::default(),
+            endianness: Endianness::default(),
+            write_cycle: None,
+            clock: Instant::now,
+            pointer_query: None,
+            unlock: None,
+            last_read: None,
+            byte_budget: None,
+        }
+    }
+}
+
+/// Receive a write of `sequence` to `register`, not acknowledging any byte
+/// that does not match.
+async fn receive_unlock<W: AsyncWriteTransaction>(
+    handler: W,
+    endianness: Endianness,
+    (register, sequence): (u16, &[u8]),
+) -> bool {
+    let mut addr = [0u8; 2];
+    let Ok(WriteResult::Partial(mut handler)) = handler.handle_part(&mut addr).await else {
+        return false;
+    };
+    if endianness.decode(addr) != register {
+        return false;
+    }
+    for &expected in sequence {
+        let mut byte = [0];
+        match handler.handle_part(&mut byte).await {
+            Ok(WriteResult::Partial(next)) if byte[0] == expected => handler = next,
+            _ => return false,
+        }
+    }
+    // The write must end here
+    matches!(
+        handler.handle_part(&mut [0]).await,
+        Ok(WriteResult::Complete(0))
+    )
+}
+
+/// Bytes on the bus for a read of `size` bytes from `data`.
+fn served(data: &[u8], size: usize) -> Vec<u8> {
+    data.iter()
+        .copied()
+        .chain(std::iter::repeat(0xFF))
+        .take(size)
+        .collect()
+}
+
+/// Configure `i2c` to respond to [`TARGET_ADDR`] only, as done by
+/// [`target_service`] and [`target_service_with_config`]. The address is
+/// reported by [`AsyncI2cTarget::addresses`].
+pub fn ram_target<I: AsyncI2cTarget>(i2c: I) -> FixedAddressTarget<I> {
+    FixedAddressTarget::new(i2c, TARGET_ADDR.unwrap())
+}
+
+pub async fn target_service<I: AsyncI2cTarget>(i2c: I, stop: &AtomicBool)
+where
+    <I as AsyncI2cTarget>::Error: std::fmt::Debug,
+{
+    target_service_with_config(i2c, Config::default(), stop).await
+}
+
+pub async fn target_service_with_config<I: AsyncI2cTarget>(
+    i2c: I,
+    config: Config,
+    stop: &AtomicBool,
+) where
+    <I as AsyncI2cTarget>::Error: std::fmt::Debug,
+{
+    let i2c = ram_target(i2c);
+    match config.byte_budget {
+        Some(budget) => serve(LengthLimitedTarget::new(i2c, budget), config, stop).await,
+        None => serve(i2c, config, stop).await,
+    }
+}
+
+async fn serve<I: AsyncI2cTarget>(mut i2c: I, config: Config, stop: &AtomicBool)
 where
     <I as AsyncI2cTarget>::Error: std::fmt::Debug,
 {
@@ -21,10 +176,37 @@ where
     let mut cur_addr = 0usize;
 
     let mut expect_read = false;
+    let mut query_pointer = false;
+    let mut locked = config.unlock.is_some();
+
+    let mut wrote_data = false;
+    let mut busy_until = None;
+    let is_busy = |busy_until: Option<Instant>| busy_until.is_some_and(|t| (config.clock)() < t);
+    let record_read = |data: &[u8], size: usize| {
+        if let Some(last_read) = &config.last_read {
+            *last_read.lock().unwrap() = served(data, size);
+        }
+    };
 
     while !stop.load(Ordering::Relaxed) {
+        if i2c.take_reset() {
+            // The device lost power, so start over like it would
+            buf.fill(0);
+            cur_addr = 0;
+            expect_read = false;
+            query_pointer = false;
+            locked = config.unlock.is_some();
+            wrote_data = false;
+            busy_until = None;
+            info!("Reset detected");
+        }
+
         let mut addr = [0u8; 2];
-        let result = if expect_read && cur_addr < BUFLEN {
+        let result = if is_busy(busy_until) || locked {
+            // Don't let the expect functions acknowledge anything during the write cycle, or
+            // before unlocking
+            i2c.listen().await.map(TransactionExpectEither::from)
+        } else if expect_read && !query_pointer && cur_addr < BUFLEN {
             i2c.listen_expect_read(
                 TARGET_ADDR.unwrap(),
                 buf.get(cur_addr..).unwrap_or_default(),
@@ -43,8 +225,36 @@ where
         match result {
             Deselect => {
                 expect_read = false;
+                if config.deselect == DeselectBehavior::ResetPointer {
+                    cur_addr = 0;
+                }
+                if wrote_data {
+                    wrote_data = false;
+                    busy_until = config.write_cycle.map(|t| (config.clock)() + t);
+                }
                 info!("Deselection detected");
             }
+            Read { handler, .. } if is_busy(busy_until) => {
+                info!("Rejected read transaction, write cycle in progress");
+                drop(handler);
+            }
+            Read { handler, .. } if locked => {
+                info!("Rejected read transaction, not unlocked");
+                drop(handler);
+            }
+            Write { handler, .. } if locked => {
+                let unlock = config.unlock.unwrap();
+                locked = !receive_unlock(handler, config.endianness, unlock).await;
+                info!("Unlock attempt, unlocked: {}", !locked);
+            }
+            Read { handler, .. } if query_pointer => {
+                query_pointer = false;
+                // The pointer never exceeds BUFLEN, so it fits
+                let pointer = config.endianness.encode(cur_addr as u16);
+                let size = handler.handle_complete(&pointer, 0xFF).await.unwrap();
+                info!("Provided address pointer {}", cur_addr);
+                record_read(&pointer, size);
+            }
             Read { handler, .. } => {
                 if cur_addr >= BUFLEN {
                     // No valid address, so can't facilitate a read, nack it.
@@ -60,6 +270,7 @@ where
                         "Read transaction starting at addr {}, provided {} bytes",
                         cur_addr, size
                     );
+                    record_read(&buf[cur_addr..], size);
                     cur_addr = cur_addr.saturating_add(size).min(BUFLEN);
                 }
             }
@@ -68,6 +279,7 @@ where
                     "Expected read transaction starting at addr {}, provided {} bytes",
                     cur_addr, size
                 );
+                record_read(buf.get(cur_addr..).unwrap_or_default(), size);
                 cur_addr = cur_addr.saturating_add(size).min(BUFLEN);
             }
             ExpectedPartialRead { handler } => {
@@ -77,14 +289,20 @@ where
                     "Expected partial read transaction starting at addr {}, provided {} bytes",
                     cur_addr, size
                 );
+                record_read(buf.get(cur_addr..).unwrap_or_default(), size);
                 cur_addr = cur_addr.saturating_add(size).min(BUFLEN);
             }
+            Write { handler, .. } if is_busy(busy_until) => {
+                info!("Rejected write transaction, write cycle in progress");
+                drop(handler);
+            }
             Write { handler, .. } => {
                 info!("Write request");
                 let mut addr = [0u8; 2];
                 match handler.handle_part(&mut addr).await.unwrap() {
                     WriteResult::Partial(handler) => {
-                        let new_addr: usize = u16::from_le_bytes(addr).into();
+                        query_pointer = false;
+                        let new_addr: usize = config.endianness.decode(addr).into();
                         if new_addr < BUFLEN {
                             cur_addr = new_addr;
                             info!("Received addr {}", cur_addr);
@@ -93,12 +311,17 @@ where
                             let size_written =
                                 handler.handle_complete(&mut buf[cur_addr..]).await.unwrap();
                             cur_addr += size_written;
+                            wrote_data |= size_written > 0;
                             info!("Received write of {} bytes to ram", size_written);
                         } else {
                             // Invalid address, nack it
                             drop(handler);
                         }
                     }
+                    WriteResult::Complete(1) if config.pointer_query == Some(addr[0]) => {
+                        info!("Address pointer query received");
+                        query_pointer = true;
+                    }
                     WriteResult::Complete(size) => {
                         info!(
                             "Incomplete address write of size {} received, ignoring",
@@ -107,6 +330,10 @@ where
                     }
                 };
             }
+            ExpectedCompleteWrite { size: 1 } if config.pointer_query == Some(addr[0]) => {
+                info!("Expected address pointer query received");
+                query_pointer = true;
+            }
             ExpectedCompleteWrite { size } => {
                 info!(
                     "Expected incomplete address write of size {} received, ignoring",
@@ -115,7 +342,8 @@ where
             }
             ExpectedPartialWrite { handler } => {
                 info!("Expected partial write");
-                let new_addr: usize = u16::from_le_bytes(addr).into();
+                query_pointer = false;
+                let new_addr: usize = config.endianness.decode(addr).into();
                 if new_addr < BUFLEN {
                     cur_addr = new_addr;
                     info!("Received addr {}", cur_addr);
@@ -123,6 +351,7 @@ where
 
                     let size_written = handler.handle_complete(&mut buf[cur_addr..]).await.unwrap();
                     cur_addr += size_written;
+                    wrote_data |= size_written > 0;
                     info!("Received write of {} bytes to ram", size_written);
                 } else {
                     // Invalid address, nack it
@@ -1,10 +1,20 @@
-use embedded_hal_i2c::{AnyAddress, SevenBitAddress};
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_i2c::controller_ext::{AsyncI2cControllerExt, VerifyError, VerifyMismatch};
+use embedded_hal_i2c::{
+    AnyAddress, AsyncI2cController, AsyncI2cTarget, ErrorKind, NoAcknowledgeSource, SevenBitAddress,
+};
 use i2c_ram::driver::Error::OutOfBounds;
 use i2c_ram::driver::I2cRam;
-use i2c_ram::{TARGET_ADDR, target_service};
+use i2c_ram::{
+    Config, DeselectBehavior, Endianness, TARGET_ADDR, ram_target, target_service,
+    target_service_with_config,
+};
 use simulator::controller::SimController;
-use simulator::simulator;
+use simulator::mock::{Expectation, MockController};
+use simulator::{run_blocking_controller, simulator};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 async fn run_with(test: impl AsyncFnOnce(I2cRam<SimController, SevenBitAddress>)) {
     let _ = env_logger::try_init();
@@ -46,3 +56,422 @@ async fn basic_rw() {
     })
     .await;
 }
+
+#[tokio::test]
+async fn power_cycle_clears_ram() {
+    let _ = env_logger::try_init();
+    let (mut c, mut t) = simulator();
+    let reset = Arc::new(AtomicBool::new(false));
+    t.set_reset_signal(Some(reset.clone()));
+    let stop = AtomicBool::new(false);
+    let config = Config {
+        pointer_query: Some(0xA5),
+        ..Config::default()
+    };
+    let Some(AnyAddress::Seven(addr)) = TARGET_ADDR else {
+        panic!("Target Address wrong")
+    };
+
+    let client = async {
+        c.write(addr, &[0x04, 0x00, 1, 2, 3, 4, 5, 6, 7, 8])
+            .await
+            .unwrap();
+        let mut pointer = [0; 2];
+        c.write_read(addr, &[0xA5], &mut pointer).await.unwrap();
+        assert_eq!(u16::from_le_bytes(pointer), 12);
+
+        reset.store(true, Ordering::Relaxed);
+
+        c.write_read(addr, &[0xA5], &mut pointer).await.unwrap();
+        assert_eq!(u16::from_le_bytes(pointer), 0);
+        let mut buf = [0xAA; 16];
+        c.write_read(addr, &[0x00, 0x00], &mut buf).await.unwrap();
+        assert_eq!(buf, [0; 16]);
+
+        stop.store(true, Ordering::Relaxed);
+        drop(c);
+    };
+
+    tokio::join!(client, target_service_with_config(t, config, &stop));
+}
+
+#[tokio::test]
+async fn mock_controller() {
+    let Some(AnyAddress::Seven(addr)) = TARGET_ADDR else {
+        panic!("Target Address wrong")
+    };
+
+    let chunk = |address: u16, data: &[u8]| {
+        let mut bytes = address.to_le_bytes().to_vec();
+        bytes.extend_from_slice(data);
+        Expectation::write(addr, bytes)
+    };
+    let mut mock = MockController::new([
+        Expectation::write_read(addr, vec![0x10, 0], vec![1, 2, 3, 4]),
+        chunk(0x100, &[9; 16]),
+        chunk(0x110, &[9; 4]),
+        Expectation::write_read(addr, vec![0, 2], vec![0; 1])
+            .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data)),
+    ]);
+
+    let mut ram = I2cRam::new(&mut mock, addr);
+
+    let mut buf = [0; 4];
+    ram.read(0x10, &mut buf).await.unwrap();
+    assert_eq!(buf, [1, 2, 3, 4]);
+
+    ram.write(0x100, &[9; 20]).await.unwrap();
+
+    let err = ram.read(0x200, &mut [0]).await.unwrap_err();
+    assert_eq!(err, OutOfBounds);
+
+    mock.done();
+}
+
+#[tokio::test]
+async fn deselect_behavior() {
+    let _ = env_logger::try_init();
+
+    for (deselect, expected) in [
+        (DeselectBehavior::RetainPointer, [5, 6]),
+        (DeselectBehavior::ResetPointer, [1, 2]),
+    ] {
+        let (mut c, t) = simulator();
+        let stop = AtomicBool::new(false);
+        let config = Config {
+            deselect,
+            ..Config::default()
+        };
+        let Some(AnyAddress::Seven(addr)) = TARGET_ADDR else {
+            panic!("Target Address wrong")
+        };
+
+        let client = async {
+            c.write(addr, &[0, 0, 1, 2, 3, 4, 5, 6]).await.unwrap();
+            // Set the pointer, then STOP before reading
+            c.write(addr, &[4, 0]).await.unwrap();
+
+            let mut buf = [0; 2];
+            c.read(addr, &mut buf).await.unwrap();
+            assert_eq!(buf, expected);
+
+            stop.store(true, Ordering::Relaxed);
+            drop(c);
+        };
+
+        tokio::join!(client, target_service_with_config(t, config, &stop));
+    }
+}
+
+#[tokio::test]
+async fn target_reports_address() {
+    let (_, t) = simulator();
+    assert_eq!(t.addresses().count(), 0);
+    assert!(ram_target(t).addresses().eq([AnyAddress::Seven(0x20)]));
+
+    let (mut c, t) = simulator();
+    let stop = AtomicBool::new(false);
+    let client = async {
+        let mut buf = [0xAA; 4];
+        let err = c.write_read(0x21_u8, &[0, 0], &mut buf).await.unwrap_err();
+        assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        c.write_read(0x20_u8, &[0, 0], &mut buf).await.unwrap();
+        assert_eq!(buf, [0; 4]);
+
+        stop.store(true, Ordering::Relaxed);
+        drop(c);
+    };
+
+    tokio::join!(client, target_service(t, &stop));
+}
+
+#[tokio::test(start_paused = true)]
+async fn write_cycle() {
+    let _ = env_logger::try_init();
+    let (mut c, t) = simulator();
+    let stop = AtomicBool::new(false);
+    let config = Config {
+        write_cycle: Some(Duration::from_millis(5)),
+        clock: || tokio::time::Instant::now().into_std(),
+        ..Config::default()
+    };
+    let Some(AnyAddress::Seven(addr)) = TARGET_ADDR else {
+        panic!("Target Address wrong")
+    };
+
+    let client = async {
+        c.write(addr, &[0, 0, 1, 2, 3]).await.unwrap();
+
+        let mut buf = [0; 3];
+        let err = c.write_read(addr, &[0, 0], &mut buf).await.unwrap_err();
+        assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+
+        tokio::time::advance(Duration::from_millis(5)).await;
+
+        c.write_read(addr, &[0, 0], &mut buf).await.unwrap();
+        assert_eq!(buf, [1, 2, 3]);
+
+        stop.store(true, Ordering::Relaxed);
+        drop(c);
+    };
+
+    tokio::join!(client, target_service_with_config(t, config, &stop));
+}
+
+#[tokio::test]
+async fn last_read_includes_fill() {
+    let _ = env_logger::try_init();
+    let (mut c, t) = simulator();
+    let stop = AtomicBool::new(false);
+    let last_read = Arc::new(Mutex::new(Vec::new()));
+    let config = Config {
+        last_read: Some(last_read.clone()),
+        ..Config::default()
+    };
+    let Some(AnyAddress::Seven(addr)) = TARGET_ADDR else {
+        panic!("Target Address wrong")
+    };
+
+    let client = async {
+        c.write(addr, &[0xFE, 0x01, 1, 2]).await.unwrap();
+
+        let mut buf = [0; 4];
+        c.write_read(addr, &[0xFE, 0x01], &mut buf).await.unwrap();
+        assert_eq!(buf, [1, 2, 0xFF, 0xFF]);
+        // Make sure the target finished handling the read
+        c.write(addr, &[0, 0]).await.unwrap();
+        assert_eq!(*last_read.lock().unwrap(), [1, 2, 0xFF, 0xFF]);
+
+        stop.store(true, Ordering::Relaxed);
+        drop(c);
+    };
+
+    tokio::join!(client, target_service_with_config(t, config, &stop));
+}
+
+#[tokio::test]
+async fn byte_budget() {
+    let _ = env_logger::try_init();
+    let (mut c, t) = simulator();
+    let stop = AtomicBool::new(false);
+    let config = Config {
+        byte_budget: Some(6),
+        ..Config::default()
+    };
+    let Some(AnyAddress::Seven(addr)) = TARGET_ADDR else {
+        panic!("Target Address wrong")
+    };
+
+    let client = async {
+        let mut huge = vec![0x11; 102];
+        huge[..2].copy_from_slice(&[0, 0]);
+        let err = c.write(addr, &huge).await.unwrap_err();
+        assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data));
+
+        // Only the data within the budget was stored
+        let mut buf = [0; 6];
+        c.write_read(addr, &[0, 0], &mut buf).await.unwrap();
+        assert_eq!(buf, [0x11, 0x11, 0x11, 0x11, 0, 0]);
+
+        stop.store(true, Ordering::Relaxed);
+        drop(c);
+    };
+
+    tokio::join!(client, target_service_with_config(t, config, &stop));
+}
+
+#[test]
+fn blocking_controller() {
+    let _ = env_logger::try_init();
+    let (mut c, mut t) = simulator();
+    t.set_address(TARGET_ADDR.unwrap());
+    let stop = AtomicBool::new(false);
+    let Some(AnyAddress::Seven(addr)) = TARGET_ADDR else {
+        panic!("Target Address wrong")
+    };
+
+    let buf = run_blocking_controller(
+        move || {
+            embedded_hal_i2c::SyncI2cController::write(&mut c, addr, &[0x10, 0, 1, 2, 3]).unwrap();
+            let mut buf = [0; 4];
+            embedded_hal_i2c::SyncI2cController::write_read(&mut c, addr, &[0x10, 0], &mut buf)
+                .unwrap();
+            buf
+        },
+        target_service(t, &stop),
+    );
+    assert_eq!(buf, [1, 2, 3, 0]);
+}
+
+#[tokio::test]
+async fn big_endian() {
+    let _ = env_logger::try_init();
+    let (mut c, t) = simulator();
+    let stop = AtomicBool::new(false);
+    let config = Config {
+        endianness: Endianness::Big,
+        ..Config::default()
+    };
+    let Some(AnyAddress::Seven(addr)) = TARGET_ADDR else {
+        panic!("Target Address wrong")
+    };
+
+    let client = async {
+        let mut ram = I2cRam::new(c.clone(), addr).with_endianness(Endianness::Big);
+        ram.write(0x0102, &[9, 8]).await.unwrap();
+        let mut buf = [0; 2];
+        ram.read(0x0102, &mut buf).await.unwrap();
+        assert_eq!(buf, [9, 8]);
+
+        // The most significant byte goes first on the bus
+        c.write_read(addr, &[0x01, 0x02], &mut buf).await.unwrap();
+        assert_eq!(buf, [9, 8]);
+
+        stop.store(true, Ordering::Relaxed);
+        drop(ram);
+        drop(c);
+    };
+
+    tokio::join!(client, target_service_with_config(t, config, &stop));
+}
+
+#[tokio::test]
+async fn pointer_query() {
+    let _ = env_logger::try_init();
+    let (mut c, t) = simulator();
+    let stop = AtomicBool::new(false);
+    let config = Config {
+        pointer_query: Some(0xA5),
+        ..Config::default()
+    };
+    let Some(AnyAddress::Seven(addr)) = TARGET_ADDR else {
+        panic!("Target Address wrong")
+    };
+
+    let client = async {
+        // Leaves the pointer at 0x0105
+        c.write(addr, &[0x00, 0x01, 1, 2, 3, 4, 5]).await.unwrap();
+
+        let mut pointer = [0; 2];
+        c.write_read(addr, &[0xA5], &mut pointer).await.unwrap();
+        assert_eq!(u16::from_le_bytes(pointer), 0x0105);
+
+        // Only the next read is affected
+        let mut buf = [0; 2];
+        c.write_read(addr, &[0x00, 0x01], &mut buf).await.unwrap();
+        assert_eq!(buf, [1, 2]);
+
+        stop.store(true, Ordering::Relaxed);
+        drop(c);
+    };
+
+    tokio::join!(client, target_service_with_config(t, config, &stop));
+}
+
+#[tokio::test]
+async fn unlock_sequence() {
+    let _ = env_logger::try_init();
+    let (mut c, t) = simulator();
+    let stop = AtomicBool::new(false);
+    let config = Config {
+        unlock: Some((0x01FF, &[0xAA, 0x55])),
+        ..Config::default()
+    };
+    let Some(AnyAddress::Seven(addr)) = TARGET_ADDR else {
+        panic!("Target Address wrong")
+    };
+
+    let client = async {
+        let mut buf = [0; 2];
+        let err = c.write_read(addr, &[0, 0], &mut buf).await.unwrap_err();
+        assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data));
+        let err = c.read(addr, &mut buf).await.unwrap_err();
+        assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        let err = c.write(addr, &[0xFF, 0x01, 0xAA, 0x00]).await.unwrap_err();
+        assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data));
+
+        c.write(addr, &[0xFF, 0x01, 0xAA, 0x55]).await.unwrap();
+
+        c.write(addr, &[0, 0, 1, 2]).await.unwrap();
+        c.write_read(addr, &[0, 0], &mut buf).await.unwrap();
+        assert_eq!(buf, [1, 2]);
+
+        stop.store(true, Ordering::Relaxed);
+        drop(c);
+    };
+
+    tokio::join!(client, target_service_with_config(t, config, &stop));
+}
+
+struct TokioDelay;
+
+impl DelayNs for TokioDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        tokio::time::sleep(Duration::from_nanos(ns.into())).await;
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn ack_polling() {
+    let _ = env_logger::try_init();
+    let (mut c, t) = simulator();
+    let stop = AtomicBool::new(false);
+    let config = Config {
+        write_cycle: Some(Duration::from_millis(5)),
+        clock: || tokio::time::Instant::now().into_std(),
+        ..Config::default()
+    };
+    let Some(AnyAddress::Seven(addr)) = TARGET_ADDR else {
+        panic!("Target Address wrong")
+    };
+
+    let client = async {
+        c.write(addr, &[0, 0, 1, 2, 3]).await.unwrap();
+
+        // Without any time to wait, the write cycle is still busy
+        let err = c.wait_ready(addr, &mut TokioDelay, 0).await.unwrap_err();
+        assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+
+        let start = tokio::time::Instant::now();
+        c.wait_ready(addr, &mut TokioDelay, 100_000).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(10));
+
+        let mut buf = [0; 3];
+        c.write_read(addr, &[0, 0], &mut buf).await.unwrap();
+        assert_eq!(buf, [1, 2, 3]);
+
+        stop.store(true, Ordering::Relaxed);
+        drop(c);
+    };
+
+    tokio::join!(client, target_service_with_config(t, config, &stop));
+}
+
+#[tokio::test]
+async fn write_verify() {
+    let _ = env_logger::try_init();
+    let (mut c, t) = simulator();
+    let stop = AtomicBool::new(false);
+    let Some(AnyAddress::Seven(addr)) = TARGET_ADDR else {
+        panic!("Target Address wrong")
+    };
+
+    let client = async {
+        let mut buf = [0; 8];
+        c.write_verify(addr, &[0x10, 0], &[1, 2, 3], &mut buf)
+            .await
+            .unwrap();
+
+        c.inject_read_bit_error(1, 0x04);
+        let err = c
+            .write_verify(addr, &[0x10, 0], &[1, 2, 3], &mut buf)
+            .await
+            .unwrap_err();
+        assert_eq!(err, VerifyError::Mismatch(VerifyMismatch { offset: 1 }));
+
+        stop.store(true, Ordering::Relaxed);
+        drop(c);
+    };
+
+    tokio::join!(client, target_service(t, &stop));
+}
@@ -0,0 +1,95 @@
+//! Overhead of the default `handle_complete` compared to a manual loop of single-byte `handle_part`
+//! calls, against [`SimTarget`](simulator::target::SimTarget) without clock stretching.
+//!
+//! Run with `cargo bench -p simulator`. Baseline measured on a Linux x86-64 container, per
+//! transaction:
+//!
+//! | operation | size | `handle_complete` | `handle_part` loop |
+//! |-----------|------|-------------------|--------------------|
+//! | write     | 4    | 6.1 µs            | 6.6 µs             |
+//! | write     | 64   | 13 µs             | 19 µs              |
+//! | write     | 1024 | 120 µs            | 208 µs             |
+//! | read      | 4    | 10.6 µs           | 8.6 µs             |
+//! | read      | 64   | 21 µs             | 19 µs              |
+//! | read      | 1024 | 112 µs            | 182 µs             |
+//!
+//! For small transfers the time goes into passing the transaction between the controller and the
+//! target, and the difference is within the noise. Only large transfers clearly benefit from
+//! handling more than a byte at a time.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use embedded_hal_i2c::{
+    AsyncI2cController, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction, ReadResult,
+    Transaction, WriteResult,
+};
+use simulator::simulator;
+use simulator::target::SimTarget;
+
+const A7: u8 = 0x42;
+const SIZES: [usize; 3] = [4, 64, 1024];
+
+/// Handle the next transaction, plus the deselect after it.
+async fn serve(t: &mut SimTarget, buf: &mut [u8], complete: bool) {
+    match t.listen().await.unwrap() {
+        Transaction::Write { mut handler, .. } if !complete => {
+            for byte in buf.chunks_mut(1) {
+                match handler.handle_part(byte).await.unwrap() {
+                    WriteResult::Partial(next) => handler = next,
+                    WriteResult::Complete(_) => break,
+                }
+            }
+        }
+        Transaction::Write { handler, .. } => {
+            handler.handle_complete(buf).await.unwrap();
+        }
+        Transaction::Read { mut handler, .. } if !complete => {
+            for byte in buf.chunks(1) {
+                match handler.handle_part(byte).await.unwrap() {
+                    ReadResult::Partial(next) => handler = next,
+                    ReadResult::Complete(_) => break,
+                }
+            }
+        }
+        Transaction::Read { handler, .. } => {
+            handler.handle_complete(buf, 0xFF).await.unwrap();
+        }
+        Transaction::Deselect => panic!("Expected a transaction"),
+    }
+    assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+}
+
+fn bench(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let (mut controller, mut target) = simulator();
+    let mut data = vec![0; 1024];
+    // One byte extra, so the target sees the end of a write
+    let mut buf = vec![0; 1025];
+
+    for (name, write) in [("write", true), ("read", false)] {
+        let mut group = c.benchmark_group(name);
+        for size in SIZES {
+            for (method, complete) in [("handle_complete", true), ("handle_part", false)] {
+                group.bench_function(BenchmarkId::new(method, size), |b| {
+                    b.iter(|| {
+                        runtime.block_on(async {
+                            let control = async {
+                                if write {
+                                    controller.write(A7, &data[..size]).await.unwrap();
+                                } else {
+                                    controller.read(A7, &mut data[..size]).await.unwrap();
+                                }
+                            };
+                            tokio::join!(control, serve(&mut target, &mut buf[..=size], complete));
+                        })
+                    })
+                });
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
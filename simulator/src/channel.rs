@@ -0,0 +1,251 @@
+//! Software-defined register device forwarding writes to application logic
+
+use embedded_hal_i2c::register::{RegisterAccess, RegisterAccessListener, RegisterAddressWidth};
+use embedded_hal_i2c::{AnyAddress, AsyncI2cTarget, AsyncReadTransaction};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::Sender;
+
+/// Address of a register of a [`ChannelTarget`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RegAddr(pub u8);
+
+impl From<u8> for RegAddr {
+    fn from(value: u8) -> Self {
+        Self(value)
+    }
+}
+
+impl From<RegAddr> for u8 {
+    fn from(value: RegAddr) -> Self {
+        value.0
+    }
+}
+
+/// Number of bytes transferred to or from a register
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ByteLen(pub usize);
+
+impl From<usize> for ByteLen {
+    fn from(value: usize) -> Self {
+        Self(value)
+    }
+}
+
+impl From<ByteLen> for usize {
+    fn from(value: ByteLen) -> Self {
+        value.0
+    }
+}
+
+/// Contents of the registers served on reads, shared with the application
+pub type Registers = Arc<Mutex<HashMap<RegAddr, Vec<u8>>>>;
+
+/// Queue of values served by a FIFO register, shared with the application
+pub type Fifo = Arc<Mutex<VecDeque<Vec<u8>>>>;
+
+/// Register write received by a [`ChannelTarget`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterWrite {
+    /// The register written
+    pub register: RegAddr,
+    /// The data written to it
+    pub data: Vec<u8>,
+}
+
+impl RegisterWrite {
+    /// Number of bytes written to the register.
+    pub fn size(&self) -> ByteLen {
+        ByteLen(self.data.len())
+    }
+}
+
+/// Read of a register of a [`ChannelTarget`] with a different length than set with
+/// [`ChannelTarget::set_read_length`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadLengthMismatch {
+    /// The register read
+    pub register: RegAddr,
+    /// The length the device requires
+    pub expected: ByteLen,
+    /// The number of bytes the controller actually read, including any overrun characters
+    pub actual: ByteLen,
+}
+
+/// Register device built on any [`AsyncI2cTarget`], as a building block for test rigs
+///
+/// Every completed register write is sent as a [`RegisterWrite`] to the application. Register
+/// reads are answered from a shared snapshot of the register contents, which the application
+/// updates as it sees fit. Reads of registers missing from the snapshot only return the overrun
+/// character `0xFF`. Writes are not applied to the snapshot.
+///
+/// Registers with a value computed on every access, like a free-running counter, are served by
+/// callbacks set with [`Self::set_on_read`] and [`Self::set_on_write`] instead.
+pub struct ChannelTarget<T> {
+    target: T,
+    listener: RegisterAccessListener,
+    events: Sender<RegisterWrite>,
+    registers: Registers,
+    on_read: HashMap<RegAddr, OnReadFn>,
+    on_write: HashMap<RegAddr, OnWriteFn>,
+    read_lengths: HashMap<RegAddr, ByteLen>,
+    mismatches: Vec<ReadLengthMismatch>,
+}
+
+type OnReadFn = Box<dyn FnMut() -> Vec<u8> + Send>;
+type OnWriteFn = Box<dyn FnMut(&[u8]) + Send>;
+
+impl<T: AsyncI2cTarget> ChannelTarget<T> {
+    /// Serve the registers of the device at `address` on `target`.
+    pub fn new(
+        target: T,
+        address: AnyAddress,
+        events: Sender<RegisterWrite>,
+        registers: Registers,
+    ) -> Self {
+        Self {
+            target,
+            listener: RegisterAccessListener::new(address, RegisterAddressWidth::OneByte),
+            events,
+            registers,
+            on_read: HashMap::new(),
+            on_write: HashMap::new(),
+            read_lengths: HashMap::new(),
+            mismatches: Vec::new(),
+        }
+    }
+
+    /// Set the power-on contents of the registers in `defaults`, like the defaults listed in a
+    /// datasheet.
+    ///
+    /// These are stored in the shared register snapshot, replacing the values there.
+    pub fn with_defaults(self, defaults: &[(RegAddr, &[u8])]) -> Self {
+        let mut registers = self.registers.lock().unwrap();
+        for (register, value) in defaults {
+            registers.insert(*register, value.to_vec());
+        }
+        drop(registers);
+        self
+    }
+
+    /// Answer reads of `register` with the value returned by `on_read`, called on every read.
+    pub fn set_on_read(
+        &mut self,
+        register: impl Into<RegAddr>,
+        on_read: impl FnMut() -> Vec<u8> + Send + 'static,
+    ) {
+        self.on_read.insert(register.into(), Box::new(on_read));
+    }
+
+    /// Answer every read of `register` with the next value popped from `fifo`, or with `empty` once
+    /// it is drained.
+    pub fn set_fifo(&mut self, register: impl Into<RegAddr>, fifo: Fifo, empty: Vec<u8>) {
+        self.set_on_read(register, move || {
+            fifo.lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or_else(|| empty.clone())
+        });
+    }
+
+    /// Serve `register` as an SMBus block: reads return a count byte, followed by that many bytes
+    /// of the register contents in the shared snapshot.
+    ///
+    /// A block holds at most 255 bytes, longer contents are truncated.
+    pub fn set_block_read(&mut self, register: impl Into<RegAddr>) {
+        let register = register.into();
+        let registers = self.registers.clone();
+        self.set_on_read(register, move || {
+            let registers = registers.lock().unwrap();
+            let data = registers.get(&register).map_or(&[][..], Vec::as_slice);
+            let data = &data[..data.len().min(usize::from(u8::MAX))];
+            let mut block = Vec::with_capacity(data.len() + 1);
+            block.push(data.len() as u8);
+            block.extend_from_slice(data);
+            block
+        });
+    }
+
+    /// Pass writes to `register` to `on_write`, instead of sending them to the application.
+    pub fn set_on_write(
+        &mut self,
+        register: impl Into<RegAddr>,
+        on_write: impl FnMut(&[u8]) + Send + 'static,
+    ) {
+        self.on_write.insert(register.into(), Box::new(on_write));
+    }
+
+    /// Require reads of `register` to be exactly `len` bytes long.
+    ///
+    /// The controller decides the length of a read, so a read of another length is still served as
+    /// usual. It is recorded as a [`ReadLengthMismatch`] though, to be checked with
+    /// [`Self::read_length_mismatches`].
+    pub fn set_read_length(&mut self, register: impl Into<RegAddr>, len: impl Into<ByteLen>) {
+        self.read_lengths.insert(register.into(), len.into());
+    }
+
+    /// All reads of a different length than set with [`Self::set_read_length`], oldest first.
+    pub fn read_length_mismatches(&self) -> &[ReadLengthMismatch] {
+        &self.mismatches
+    }
+
+    /// Handle transactions until `stop` is set, or the receiver of the events is dropped.
+    pub async fn run(&mut self, stop: &AtomicBool) {
+        let mut buf = [0; 256];
+        while !stop.load(Ordering::Relaxed) {
+            let Ok(access) = self.listener.listen(&mut self.target, &mut buf).await else {
+                continue;
+            };
+            match access {
+                RegisterAccess::Write { register, size } => {
+                    let register = RegAddr(register as u8);
+                    if let Some(on_write) = self.on_write.get_mut(&register) {
+                        on_write(&buf[..size]);
+                        continue;
+                    }
+                    let event = RegisterWrite {
+                        register,
+                        data: buf[..size].to_vec(),
+                    };
+                    if self.events.send(event).await.is_err() {
+                        return;
+                    }
+                }
+                RegisterAccess::Read { register, handler } => {
+                    let register = RegAddr(register as u8);
+                    let value = match self.on_read.get_mut(&register) {
+                        Some(on_read) => on_read(),
+                        None => self
+                            .registers
+                            .lock()
+                            .unwrap()
+                            .get(&register)
+                            .cloned()
+                            .unwrap_or_default(),
+                    };
+                    let Ok(size) = handler.handle_complete(&value, 0xFF).await else {
+                        continue;
+                    };
+                    if let Some(&expected) = self.read_lengths.get(&register)
+                        && expected != ByteLen(size)
+                    {
+                        self.mismatches.push(ReadLengthMismatch {
+                            register,
+                            expected,
+                            actual: ByteLen(size),
+                        });
+                    }
+                }
+                RegisterAccess::Pointer { .. } => {}
+                // Not acknowledged
+                RegisterAccess::Other(_) => {}
+            }
+        }
+    }
+
+    /// Release the wrapped target.
+    pub fn into_inner(self) -> T {
+        self.target
+    }
+}
@@ -3,25 +3,179 @@
 #[cfg(doc)]
 use crate::target::SimTarget;
 use crate::{PartialTransaction, SimOp, SimTransaction};
+use embedded_hal_i2c::controller_ext::AsyncI2cMultiAddressController;
 use embedded_hal_i2c::{
-    AddressMode, AnyAddress, AsyncI2cController, ErrorKind, ErrorType, Operation, SyncI2cController,
+    AddressMode, AnyAddress, AsyncI2cController, ErrorKind, ErrorType, NoAcknowledgeSource,
+    Operation, SyncI2cController,
 };
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::oneshot;
 use tokio::sync::oneshot::Receiver;
+use tokio::sync::oneshot::error::TryRecvError;
 
 /// Simulated I2C controller
 ///
 /// This can be created with [`crate::simulator`], which also returns the linked [`SimTarget`].
 /// All [`AsyncI2cController::transaction`] calls on this controller are forwarded to the target
 /// as if there was a real I2C bus connecting the two.
+///
+/// The controller can be cloned to issue transactions from multiple tasks. Like on a real bus,
+/// concurrent transactions are serialized: the channel to the target only holds a single pending
+/// transaction, so other clones wait until the target has picked it up.
+#[derive(Clone)]
 pub struct SimController {
     to_target: Sender<PartialTransaction>,
+    buffers: Vec<Vec<u8>>,
+    clock_hz: Option<u32>,
+    max_stretch: Option<Duration>,
+    split_write_read: bool,
+    nack_storm: usize,
+    read_bit_error: Option<(usize, u8)>,
+    target_timeout: Option<Duration>,
 }
 
 impl SimController {
     pub(crate) const fn new(to_target: Sender<PartialTransaction>) -> Self {
-        Self { to_target }
+        Self {
+            to_target,
+            buffers: Vec::new(),
+            clock_hz: None,
+            max_stretch: None,
+            split_write_read: false,
+            nack_storm: 0,
+            read_bit_error: None,
+            target_timeout: None,
+        }
+    }
+
+    /// Pre-allocate buffers for operations of up to `hint` bytes.
+    ///
+    /// The buffers used to pass data to and from the target are reused between transactions, so
+    /// this avoids reallocation during large transfers altogether. The depth of the channel to the
+    /// target does not affect throughput, as every transaction waits for the target to finish it.
+    pub fn with_capacity(mut self, hint: usize) -> Self {
+        // Enough for a write_read
+        self.buffers = vec![Vec::with_capacity(hint), Vec::with_capacity(hint)];
+        self
+    }
+
+    /// Run the bus at `clock_hz` Hz, or at an unspecified clock if `None`.
+    ///
+    /// The simulator does not model any timing, but a target only acknowledges transactions at a
+    /// clock within the range set with
+    /// [`SimTarget::set_clock_range`](crate::target::SimTarget::set_clock_range).
+    pub fn set_clock(&mut self, clock_hz: Option<u32>) {
+        self.clock_hz = clock_hz;
+    }
+
+    /// Abort transactions in which the target stretches the clock for longer than `max_per_byte`
+    /// on a byte, or wait indefinitely if `None`, the default.
+    ///
+    /// Real controllers give up on a target holding the clock low past a hardware limit. An
+    /// aborted transaction fails with [`ErrorKind::Other`] once the limit has passed. The target
+    /// stretches as set with
+    /// [`SimTarget::set_stretch`](crate::target::SimTarget::set_stretch).
+    pub fn set_max_stretch(&mut self, max_per_byte: Option<Duration>) {
+        self.max_stretch = max_per_byte;
+    }
+
+    /// Send a STOP instead of a repeated start between a write and a following read.
+    ///
+    /// This models cheap controllers that split a `write_read` into two separate transactions,
+    /// so the target sees a [`Transaction::Deselect`](embedded_hal_i2c::Transaction::Deselect)
+    /// between the write and the read. Applies to every write followed by a read in a
+    /// `transaction`.
+    pub fn set_split_write_read(&mut self, enabled: bool) {
+        self.split_write_read = enabled;
+    }
+
+    /// Fail the next `count` transactions with [`NoAcknowledgeSource::Address`], as if the bus was
+    /// taken by contention, then resume as normal.
+    ///
+    /// The failed transactions never reach the target.
+    pub fn nack_storm(&mut self, count: usize) {
+        self.nack_storm = count;
+    }
+
+    /// Flip the bits in `mask` of byte `offset` of the next read that is long enough, as if the
+    /// bus corrupted them.
+    ///
+    /// The target is not aware of this, it provided the original data.
+    pub fn inject_read_bit_error(&mut self, offset: usize, mask: u8) {
+        self.read_bit_error = Some((offset, mask));
+    }
+
+    /// Fail transactions the target does not finish within `timeout` with [`ErrorKind::Other`],
+    /// or wait indefinitely if `None`, the default.
+    ///
+    /// Without a timeout, a transaction to a target that is alive but never calls `listen` hangs
+    /// forever, which is hard to debug in tests. A transaction to a dropped target always fails
+    /// promptly. A timed out transaction may still reach the target later on.
+    pub fn set_target_timeout(&mut self, timeout: Option<Duration>) {
+        self.target_timeout = timeout;
+    }
+
+    /// Write `bytes` to `address`, but send a STOP after the first `stop_after` of them.
+    ///
+    /// This models a controller aborting a command halfway, which the `embedded-hal` traits cannot
+    /// express as they always write whole buffers. The target receives only the first
+    /// `stop_after` bytes, followed by a
+    /// [`Transaction::Deselect`](embedded_hal_i2c::Transaction::Deselect), no matter how many
+    /// bytes it was ready to receive.
+    pub async fn write_truncated<A>(
+        &mut self,
+        address: A,
+        bytes: &[u8],
+        stop_after: usize,
+    ) -> Result<(), ErrorKind>
+    where
+        A: AddressMode + Into<AnyAddress>,
+    {
+        let len = stop_after.min(bytes.len());
+        AsyncI2cController::write(self, address, &bytes[..len]).await
+    }
+
+    /// Apply the error set with [`Self::inject_read_bit_error`] to the data read by `operations`.
+    fn corrupt_reads(&mut self, operations: &mut [Operation<'_>]) {
+        let Some((offset, mask)) = self.read_bit_error else {
+            return;
+        };
+        for op in operations {
+            if let Operation::Read(buf) = op
+                && let Some(byte) = buf.get_mut(offset)
+            {
+                *byte ^= mask;
+                self.read_bit_error = None;
+                return;
+            }
+        }
+    }
+
+    /// Fail the transaction if a storm set with [`Self::nack_storm`] is still going on.
+    fn weather_storm(&mut self) -> Result<(), ErrorKind> {
+        if self.nack_storm > 0 {
+            self.nack_storm -= 1;
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
+        Ok(())
+    }
+
+    /// Number of operations to send in the first transaction, when splitting them.
+    fn split_point(&self, operations: &[Operation<'_>]) -> usize {
+        let split = self.split_write_read.then(|| {
+            operations
+                .windows(2)
+                .position(|pair| matches!(pair, [Operation::Write(_), Operation::Read(_)]))
+        });
+        split.flatten().map_or(operations.len(), |i| i + 1)
+    }
+
+    fn buffer(&mut self) -> Vec<u8> {
+        let mut buffer = self.buffers.pop().unwrap_or_default();
+        buffer.clear();
+        buffer
     }
 }
 
@@ -30,41 +184,166 @@ impl ErrorType for SimController {
 }
 
 impl SimController {
-    fn send_transaction(
+    /// Pass `transaction` to the target and wait for its reply.
+    async fn exchange(
+        &mut self,
+        transaction: PartialTransaction,
+        receiver: Receiver<Result<SimTransaction, ErrorKind>>,
+    ) -> Result<SimTransaction, ErrorKind> {
+        let timeout = self.target_timeout;
+        let round_trip = async {
+            self.to_target
+                .send(transaction)
+                .await
+                .map_err(|_| ErrorKind::Other)?;
+            receiver.await.map_err(|_| ErrorKind::Other)?
+        };
+        match timeout {
+            None => round_trip.await,
+            Some(timeout) => tokio::time::timeout(timeout, round_trip)
+                .await
+                .map_err(|_| ErrorKind::Other)?,
+        }
+    }
+
+    /// Like [`Self::exchange`], blocking the current thread.
+    fn exchange_blocking(
+        &mut self,
+        transaction: PartialTransaction,
+        mut receiver: Receiver<Result<SimTransaction, ErrorKind>>,
+    ) -> Result<SimTransaction, ErrorKind> {
+        let Some(timeout) = self.target_timeout else {
+            self.to_target
+                .blocking_send(transaction)
+                .map_err(|_| ErrorKind::Other)?;
+            return receiver.blocking_recv().map_err(|_| ErrorKind::Other)?;
+        };
+
+        // There is no blocking send or receive with a timeout, so poll both.
+        let deadline = Instant::now() + timeout;
+        let mut pending = Some(transaction);
+        loop {
+            if let Some(transaction) = pending.take() {
+                match self.to_target.try_send(transaction) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(transaction)) => pending = Some(transaction),
+                    Err(TrySendError::Closed(_)) => return Err(ErrorKind::Other),
+                }
+            } else {
+                match receiver.try_recv() {
+                    Ok(reply) => return reply,
+                    Err(TryRecvError::Empty) => {}
+                    Err(TryRecvError::Closed) => return Err(ErrorKind::Other),
+                }
+            }
+            if Instant::now() >= deadline {
+                return Err(ErrorKind::Other);
+            }
+            std::thread::sleep(Duration::from_micros(100));
+        }
+    }
+
+    fn prepare_transaction<'a>(
         &mut self,
         address: AnyAddress,
-        operations: &mut [Operation],
-    ) -> Receiver<Result<SimTransaction, ErrorKind>> {
+        operations: impl IntoIterator<Item = (AnyAddress, &'a Operation<'a>)>,
+        context: Option<u64>,
+    ) -> (
+        PartialTransaction,
+        Receiver<Result<SimTransaction, ErrorKind>>,
+    ) {
         let actions = operations
-            .iter()
-            .map(|a| match a {
-                Operation::Read(r) => SimOp::Read(vec![0; r.len()]),
-                Operation::Write(w) => SimOp::Write(w.to_vec()),
+            .into_iter()
+            .map(|(address, a)| {
+                let mut buffer = self.buffer();
+                let op = match a {
+                    Operation::Read(r) => {
+                        buffer.resize(r.len(), 0);
+                        SimOp::Read(buffer)
+                    }
+                    Operation::Write(w) => {
+                        buffer.extend_from_slice(w);
+                        SimOp::Write(buffer)
+                    }
+                };
+                (address, op)
             })
             .collect();
 
-        let transaction = SimTransaction { address, actions };
+        let transaction = SimTransaction {
+            address,
+            actions,
+            clock_hz: self.clock_hz,
+            max_stretch: self.max_stretch,
+            context,
+        };
         let (sender, receiver) = oneshot::channel();
 
-        self.to_target
-            .try_send(PartialTransaction::new(transaction, sender))
-            .unwrap();
-        receiver
+        (PartialTransaction::new(transaction, sender), receiver)
+    }
+}
+
+impl SimController {
+    /// Perform a transaction like [`AsyncI2cController::transaction`], tagged with `context`.
+    ///
+    /// The tag does not change anything on the bus. It is shown in the transcript of the target,
+    /// and passed to its observer, to correlate the transactions seen by the target with the test
+    /// steps that started them.
+    pub async fn transaction_tagged<A>(
+        &mut self,
+        address: A,
+        operations: &mut [Operation<'_>],
+        context: u64,
+    ) -> Result<(), ErrorKind>
+    where
+        A: AddressMode + Into<AnyAddress>,
+    {
+        self.run_transaction(address.into(), operations, Some(context))
+            .await
+    }
+
+    async fn run_transaction(
+        &mut self,
+        address: AnyAddress,
+        mut operations: &mut [Operation<'_>],
+        context: Option<u64>,
+    ) -> Result<(), ErrorKind> {
+        self.weather_storm()?;
+        loop {
+            let at = self.split_point(operations);
+            let (now, later) = core::mem::take(&mut operations).split_at_mut(at);
+            let (transaction, receiver) =
+                self.prepare_transaction(address, now.iter().map(|op| (address, op)), context);
+            self.exchange(transaction, receiver)
+                .await?
+                .copy_to_ops(&mut *now, &mut self.buffers);
+            self.corrupt_reads(now);
+            if later.is_empty() {
+                return Ok(());
+            }
+            operations = later;
+        }
     }
 }
 
 impl SimTransaction {
-    fn copy_to_ops(self, operations: &mut [Operation]) {
-        let actions = self.actions;
-        for (op, reply) in operations.iter_mut().zip(actions) {
-            match (op, reply) {
+    fn copy_to_ops<'a, 'b: 'a>(
+        self,
+        operations: impl IntoIterator<Item = &'a mut Operation<'b>>,
+        buffers: &mut Vec<Vec<u8>>,
+    ) {
+        let actions = self.actions.into_iter().map(|(_, op)| op);
+        for (op, reply) in operations.into_iter().zip(actions) {
+            let buffer = match (op, reply) {
                 (Operation::Read(buf), SimOp::Read(response)) => {
                     assert_eq!(buf.len(), response.len());
                     buf.copy_from_slice(&response[..]);
+                    response
                 }
-                (Operation::Write(_), SimOp::Write(_)) => {}
+                (Operation::Write(_), SimOp::Write(sent)) => sent,
                 _ => panic!("send operation does not matched received operation"),
-            }
+            };
+            buffers.push(buffer);
         }
     }
 }
@@ -78,11 +357,7 @@ where
         address: A,
         operations: &mut [Operation<'_>],
     ) -> Result<(), Self::Error> {
-        self.send_transaction(address.into(), operations)
-            .await
-            .map_err(|_| ErrorKind::Other)??
-            .copy_to_ops(operations);
-        Ok(())
+        self.run_transaction(address.into(), operations, None).await
     }
 }
 
@@ -93,12 +368,53 @@ where
     fn transaction(
         &mut self,
         address: A,
-        operations: &mut [Operation<'_>],
+        mut operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.weather_storm()?;
+        let address = address.into();
+        loop {
+            let at = self.split_point(operations);
+            let (now, later) = core::mem::take(&mut operations).split_at_mut(at);
+            let (transaction, receiver) =
+                self.prepare_transaction(address, now.iter().map(|op| (address, op)), None);
+            self.exchange_blocking(transaction, receiver)?
+                .copy_to_ops(&mut *now, &mut self.buffers);
+            self.corrupt_reads(now);
+            if later.is_empty() {
+                return Ok(());
+            }
+            operations = later;
+        }
+    }
+}
+
+/// Transactions changing address on a repeated start, e.g. to model bus bridges.
+///
+/// Each operation is reported to the [`SimTarget`] with its own address, with a
+/// [`Transaction::Deselect`](embedded_hal_i2c::Transaction::Deselect) in between when the address
+/// changes.
+impl<A> AsyncI2cMultiAddressController<A> for SimController
+where
+    A: AddressMode + Into<AnyAddress> + Copy,
+{
+    async fn transaction_multi(
+        &mut self,
+        operations: &mut [(A, Operation<'_>)],
     ) -> Result<(), Self::Error> {
-        self.send_transaction(address.into(), operations)
-            .blocking_recv()
-            .map_err(|_| ErrorKind::Other)??
-            .copy_to_ops(operations);
+        self.weather_storm()?;
+        let Some(&(first, _)) = operations.first() else {
+            return Ok(());
+        };
+        let (transaction, receiver) = self.prepare_transaction(
+            first.into(),
+            operations
+                .iter()
+                .map(|(address, op)| ((*address).into(), op)),
+            None,
+        );
+        self.exchange(transaction, receiver)
+            .await?
+            .copy_to_ops(operations.iter_mut().map(|(_, op)| op), &mut self.buffers);
         Ok(())
     }
 }
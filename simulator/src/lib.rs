@@ -49,6 +49,9 @@
 
 use controller::SimController;
 use embedded_hal_i2c::{AnyAddress, ErrorKind};
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::pin;
+use std::time::Duration;
 use target::SimTarget;
 use tokio::sync::mpsc::channel;
 use tokio::sync::oneshot;
@@ -56,7 +59,13 @@ use tokio::sync::oneshot;
 #[cfg(doc)]
 use embedded_hal_i2c::AsyncI2cTarget;
 
+pub mod channel;
 pub mod controller;
+pub mod mock;
+pub mod multiplex;
+pub mod mux;
+#[cfg(feature = "net")]
+pub mod net;
 pub mod target;
 
 /// Create an I2C controller and target pair
@@ -72,22 +81,66 @@ pub fn simulator() -> (SimController, SimTarget) {
     )
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Run a blocking controller against an async target
+///
+/// `controller` is run on a new thread, to drive a [`SimController`] through
+/// [`SyncI2cController`](embedded_hal_i2c::SyncI2cController). Meanwhile `target` runs on a
+/// current-thread runtime on the calling thread, until `controller` returns. The target may finish
+/// earlier, but does not have to, so a target service that runs forever can be used directly.
+///
+/// Returns the result of `controller`. A panic on either side is propagated to the caller.
+pub fn run_blocking_controller<R: Send + 'static>(
+    controller: impl FnOnce() -> R + Send + 'static,
+    target: impl Future<Output = ()>,
+) -> R {
+    let (done, finished) = oneshot::channel();
+    let thread = std::thread::spawn(move || {
+        let _ = done.send(panic::catch_unwind(AssertUnwindSafe(controller)));
+    });
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .expect("Failed to create the runtime for the target");
+    let result = runtime.block_on(async {
+        let mut finished = pin!(finished);
+        tokio::select! {
+            result = &mut finished => result,
+            () = target => finished.await,
+        }
+    });
+    thread.join().expect("The controller thread catches panics");
+
+    match result.expect("The controller thread always reports back") {
+        Ok(result) => result,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum SimOp {
     Read(Vec<u8>),
     Write(Vec<u8>),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct SimTransaction {
+    /// Address of the initial start condition
     address: AnyAddress,
-    actions: Vec<SimOp>,
+    /// Operations, each with the address used in the (repeated) start condition before it
+    actions: Vec<(AnyAddress, SimOp)>,
+    /// Bus clock of the controller in Hz, if modeled
+    clock_hz: Option<u32>,
+    /// Longest clock stretch per byte the controller waits for, if limited
+    max_stretch: Option<Duration>,
+    /// Tag set by the user to correlate the transaction, not sent on the bus
+    context: Option<u64>,
 }
 
 #[derive(Debug)]
 struct PartialTransaction {
     transaction: SimTransaction,
     current_op: usize,
+    reported_restart: bool,
     responder: oneshot::Sender<Result<SimTransaction, ErrorKind>>,
 }
 
@@ -99,14 +152,28 @@ impl PartialTransaction {
         Self {
             transaction,
             current_op: 0,
+            reported_restart: false,
             responder,
         }
     }
 
     fn current(&self) -> Option<&SimOp> {
-        self.transaction.actions.get(self.current_op)
+        self.transaction
+            .actions
+            .get(self.current_op)
+            .map(|(_, op)| op)
     }
     fn current_mut(&mut self) -> Option<&mut SimOp> {
-        self.transaction.actions.get_mut(self.current_op)
+        self.transaction
+            .actions
+            .get_mut(self.current_op)
+            .map(|(_, op)| op)
+    }
+
+    /// Address of the current operation, if it differs from the one of the operation before.
+    fn changed_address(&self) -> Option<AnyAddress> {
+        let previous = self.current_op.checked_sub(1)?;
+        let (address, _) = self.transaction.actions.get(self.current_op)?;
+        (*address != self.transaction.actions[previous].0).then_some(*address)
     }
 }
@@ -0,0 +1,155 @@
+//! Mock controller checking transactions against a script
+//!
+//! Unlike [`crate::simulator`], this does not need a target on the other side of the bus. Each
+//! transaction issued on the [`MockController`] is compared against the next [`Expectation`], and
+//! answered with the read data from that expectation.
+
+use embedded_hal_i2c::{
+    AddressMode, AnyAddress, AsyncI2cController, ErrorKind, ErrorType, Operation, SyncI2cController,
+};
+use std::collections::VecDeque;
+
+/// Single operation within an [`Expectation`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockOp {
+    /// Expect a write of exactly these bytes
+    Write(Vec<u8>),
+    /// Expect a read of this many bytes, responding with these bytes
+    Read(Vec<u8>),
+}
+
+/// Transaction the [`MockController`] expects to receive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expectation {
+    address: AnyAddress,
+    operations: Vec<MockOp>,
+    result: Result<(), ErrorKind>,
+}
+
+impl Expectation {
+    /// Expect a transaction consisting of `operations` to `address`
+    pub fn new(address: impl Into<AnyAddress>, operations: Vec<MockOp>) -> Self {
+        Self {
+            address: address.into(),
+            operations,
+            result: Ok(()),
+        }
+    }
+
+    /// Expect a single write of `data` to `address`
+    pub fn write(address: impl Into<AnyAddress>, data: Vec<u8>) -> Self {
+        Self::new(address, vec![MockOp::Write(data)])
+    }
+
+    /// Expect a single read from `address`, responding with `response`
+    pub fn read(address: impl Into<AnyAddress>, response: Vec<u8>) -> Self {
+        Self::new(address, vec![MockOp::Read(response)])
+    }
+
+    /// Expect a write of `data` followed by a read responded to with `response`
+    pub fn write_read(address: impl Into<AnyAddress>, data: Vec<u8>, response: Vec<u8>) -> Self {
+        Self::new(address, vec![MockOp::Write(data), MockOp::Read(response)])
+    }
+
+    /// Fail the transaction with `error` instead of completing it
+    ///
+    /// No read data is provided to the controller when the transaction fails.
+    pub fn with_error(mut self, error: ErrorKind) -> Self {
+        self.result = Err(error);
+        self
+    }
+}
+
+/// Mock I2C controller
+///
+/// Panics whenever a transaction does not match the next expectation.
+pub struct MockController {
+    expectations: VecDeque<Expectation>,
+}
+
+impl MockController {
+    /// Create a controller expecting exactly `expectations`, in order
+    pub fn new(expectations: impl IntoIterator<Item = Expectation>) -> Self {
+        Self {
+            expectations: expectations.into_iter().collect(),
+        }
+    }
+
+    /// Assert that all expected transactions have happened
+    pub fn done(&mut self) {
+        assert!(
+            self.expectations.is_empty(),
+            "Not all expected transactions happened: {:?}",
+            self.expectations
+        );
+    }
+
+    fn handle(
+        &mut self,
+        address: AnyAddress,
+        operations: &mut [Operation],
+    ) -> Result<(), ErrorKind> {
+        let expectation = self
+            .expectations
+            .pop_front()
+            .unwrap_or_else(|| panic!("Unexpected transaction to {address:?}: {operations:?}"));
+
+        assert_eq!(address, expectation.address, "Unexpected address");
+        assert_eq!(
+            operations.len(),
+            expectation.operations.len(),
+            "Unexpected number of operations"
+        );
+
+        for (op, expected) in operations.iter().zip(&expectation.operations) {
+            match (op, expected) {
+                (Operation::Write(data), MockOp::Write(expected)) => {
+                    assert_eq!(data, expected, "Unexpected write data");
+                }
+                (Operation::Read(buf), MockOp::Read(response)) => {
+                    assert_eq!(buf.len(), response.len(), "Unexpected read length");
+                }
+                _ => panic!("Expected {expected:?}, got {op:?}"),
+            }
+        }
+
+        expectation.result?;
+
+        for (op, expected) in operations.iter_mut().zip(expectation.operations) {
+            if let (Operation::Read(buf), MockOp::Read(response)) = (op, expected) {
+                buf.copy_from_slice(&response);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ErrorType for MockController {
+    type Error = ErrorKind;
+}
+
+impl<A> AsyncI2cController<A> for MockController
+where
+    A: AddressMode + Into<AnyAddress>,
+{
+    async fn transaction(
+        &mut self,
+        address: A,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.handle(address.into(), operations)
+    }
+}
+
+impl<A> SyncI2cController<A> for MockController
+where
+    A: AddressMode + Into<AnyAddress>,
+{
+    fn transaction(
+        &mut self,
+        address: A,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.handle(address.into(), operations)
+    }
+}
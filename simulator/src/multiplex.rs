@@ -0,0 +1,131 @@
+//! Serving multiple addresses of one target from independent tasks
+
+use embedded_hal_i2c::{
+    AnyAddress, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction, Transaction,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{Receiver, Sender, channel};
+
+/// Longest write that is passed on to an address handler, longer writes are not acknowledged
+/// beyond this length.
+pub const MAX_WRITE: usize = 256;
+
+/// Activity on an address, reported to its [`AddressHandle`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressEvent {
+    /// The controller wrote this data
+    Write(Vec<u8>),
+    /// The controller read this many bytes, including any overrun characters
+    Read(usize),
+}
+
+struct Route {
+    address: AnyAddress,
+    events: Sender<AddressEvent>,
+    read_data: Arc<Mutex<Vec<u8>>>,
+}
+
+/// Routes the transactions of a single target to independent handlers per address
+///
+/// A target only has a single `listen` loop, and its handlers borrow the target, so they cannot be
+/// passed to other tasks. Instead, this buffers every write completely before passing it to the
+/// handler of the address, and serves reads from data the handler published beforehand.
+///
+/// This buffering comes at a price: writes are acknowledged before a handler sees them, so
+/// handlers cannot refuse data. And as reads are served from the published data right away,
+/// the response to a read cannot depend on a write directly before it, such as a register
+/// address, unless the handler publishes the new data before the controller reads.
+pub struct MultiplexTarget<T> {
+    target: T,
+    routes: Vec<Route>,
+}
+
+/// Handler side of an address served by a [`MultiplexTarget`]
+pub struct AddressHandle {
+    events: Receiver<AddressEvent>,
+    read_data: Arc<Mutex<Vec<u8>>>,
+}
+
+impl AddressHandle {
+    /// Wait for the next transaction on this address.
+    ///
+    /// Returns `None` once the [`MultiplexTarget`] is gone.
+    pub async fn next(&mut self) -> Option<AddressEvent> {
+        self.events.recv().await
+    }
+
+    /// Set the data served to the controller on following reads.
+    ///
+    /// Reads beyond the end of the data get the overrun character `0xFF`.
+    pub fn set_read_data(&self, data: impl Into<Vec<u8>>) {
+        *self.read_data.lock().unwrap() = data.into();
+    }
+}
+
+impl<T: AsyncI2cTarget> MultiplexTarget<T> {
+    /// Route the transactions of `target`. Addresses without a handler are not acknowledged.
+    pub const fn new(target: T) -> Self {
+        Self {
+            target,
+            routes: Vec::new(),
+        }
+    }
+
+    /// Create the handler for `address`, replacing any earlier handler.
+    pub fn handle(&mut self, address: AnyAddress) -> AddressHandle {
+        let (events, receiver) = channel(8);
+        let read_data = Arc::new(Mutex::new(Vec::new()));
+        self.routes.retain(|route| route.address != address);
+        self.routes.push(Route {
+            address,
+            events,
+            read_data: read_data.clone(),
+        });
+        AddressHandle {
+            events: receiver,
+            read_data,
+        }
+    }
+
+    /// Route transactions until `stop` is set.
+    pub async fn run(&mut self, stop: &AtomicBool) {
+        let mut buf = vec![0; MAX_WRITE];
+        while !stop.load(Ordering::Relaxed) {
+            let Ok(transaction) = self.target.listen().await else {
+                continue;
+            };
+            let event = match transaction {
+                Transaction::Deselect => continue,
+                Transaction::Write { address, handler } => {
+                    let Some(route) = self.routes.iter().find(|r| r.address == address) else {
+                        continue;
+                    };
+                    let Ok(size) = handler.handle_complete(&mut buf).await else {
+                        continue;
+                    };
+                    (route, AddressEvent::Write(buf[..size].to_vec()))
+                }
+                Transaction::Read { address, handler } => {
+                    let Some(route) = self.routes.iter().find(|r| r.address == address) else {
+                        continue;
+                    };
+                    let data = route.read_data.lock().unwrap().clone();
+                    let Ok(size) = handler.handle_complete(&data, 0xFF).await else {
+                        continue;
+                    };
+                    (route, AddressEvent::Read(size))
+                }
+            };
+
+            let (route, event) = event;
+            // The handler may have stopped listening, that is up to the application
+            let _ = route.events.send(event).await;
+        }
+    }
+
+    /// Release the wrapped target.
+    pub fn into_inner(self) -> T {
+        self.target
+    }
+}
@@ -0,0 +1,121 @@
+//! Simulated I2C multiplexer, splitting the bus into downstream channels
+
+use crate::controller::SimController;
+use crate::target::SimTarget;
+use crate::{PartialTransaction, SimOp, SimTransaction};
+use embedded_hal_i2c::{AnyAddress, ErrorKind, NoAcknowledgeSource};
+use tokio::sync::mpsc::{Receiver, Sender, channel};
+use tokio::sync::oneshot;
+
+/// I2C multiplexer in the style of the TCA9548A, connecting the bus of a [`SimController`] to the
+/// [`SimTarget`]s on its downstream channels
+///
+/// This can be created with [`simulator_with_mux`]. The mux has a single control register at its
+/// own address: writing a byte to it enables the channels of the bits set in it, reading it returns
+/// the current value. All channels are disabled initially. Transactions to other addresses are
+/// passed to the targets of the enabled channels, in channel order, and served by the first one
+/// acknowledging the address. Without any enabled channel acknowledging it, the address is not
+/// acknowledged.
+///
+/// Unlike a real mux, transactions are never passed to more than one target at a time, so several
+/// enabled channels cannot contend on the bus.
+pub struct SimMux {
+    address: AnyAddress,
+    control: u8,
+    from_controller: Receiver<PartialTransaction>,
+    channels: Vec<Sender<PartialTransaction>>,
+}
+
+/// Create an I2C controller connected to a [`SimMux`] at `address`, with a target on each of its
+/// `channels` downstream channels
+///
+/// The mux only passes on transactions while [`SimMux::run`] is running. Like on a TCA9548A, there
+/// are at most 8 channels.
+///
+/// # Panics
+///
+/// Panics if `channels` is more than 8.
+pub fn simulator_with_mux(
+    address: AnyAddress,
+    channels: usize,
+) -> (SimController, SimMux, Vec<SimTarget>) {
+    assert!(channels <= 8, "a mux has at most 8 channels");
+    let (to_mux, from_controller) = channel(1);
+    let (channels, targets) = (0..channels)
+        .map(|_| {
+            let (to_target, from_mux) = channel(1);
+            (to_target, SimTarget::new(from_mux))
+        })
+        .unzip();
+    let mux = SimMux {
+        address,
+        control: 0,
+        from_controller,
+        channels,
+    };
+    (SimController::new(to_mux), mux, targets)
+}
+
+impl SimMux {
+    /// The value of the control register, with a bit set for every enabled channel.
+    pub const fn control(&self) -> u8 {
+        self.control
+    }
+
+    /// Pass on transactions until all controllers are dropped.
+    ///
+    /// The targets on the channels see the mux disappear like a dropped controller once it is
+    /// dropped itself.
+    pub async fn run(&mut self) {
+        while let Some(new) = self.from_controller.recv().await {
+            let PartialTransaction {
+                transaction,
+                responder,
+                ..
+            } = new;
+            let reply = if transaction.address == self.address {
+                Ok(self.access_control(transaction))
+            } else {
+                self.forward(transaction).await
+            };
+            let _ = responder.send(reply);
+        }
+    }
+
+    /// Handle a transaction to the control register.
+    fn access_control(&mut self, mut transaction: SimTransaction) -> SimTransaction {
+        for (_, op) in &mut transaction.actions {
+            match op {
+                SimOp::Write(data) => {
+                    if let Some(&control) = data.last() {
+                        self.control = control;
+                    }
+                }
+                SimOp::Read(buf) => buf.fill(self.control),
+            }
+        }
+        transaction
+    }
+
+    /// Pass `transaction` to the enabled channels, until one acknowledges the address.
+    async fn forward(&mut self, transaction: SimTransaction) -> Result<SimTransaction, ErrorKind> {
+        let address_nak = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+        let mut reply = Err(address_nak);
+        for (i, channel) in self.channels.iter().enumerate() {
+            if self.control & (1 << i) == 0 {
+                continue;
+            }
+            let (responder, receiver) = oneshot::channel();
+            let partial = PartialTransaction::new(transaction.clone(), responder);
+            if channel.send(partial).await.is_err() {
+                // The target is gone, so nothing acknowledges on this channel
+                continue;
+            }
+            reply = receiver.await.unwrap_or(Err(ErrorKind::Other));
+            if reply != Err(address_nak) {
+                break;
+            }
+        }
+        reply
+    }
+}
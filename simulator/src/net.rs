@@ -0,0 +1,377 @@
+//! Tunneling simulated transactions over TCP, for hardware-in-the-loop setups
+//!
+//! A [`TcpController`] sends every transaction to a [`TcpTarget`] on the other end of a TCP
+//! connection, which serves it like the [`SimTarget`] of a local [`simulator`](crate::simulator).
+//!
+//! Each message is a frame of a 4-byte big-endian length, followed by that many bytes of payload.
+//! A transaction is encoded as its address, the bus clock and stretch limit of the controller, its
+//! context tag, and its operations, each with the address of its start condition, direction and
+//! length, and the data for writes. The reply is a status byte, followed by either the completed
+//! transaction, encoded the same way but carrying the data of reads, or the [`ErrorKind`] it
+//! failed with.
+
+use crate::target::SimTarget;
+use crate::{PartialTransaction, SimOp, SimTransaction};
+use embedded_hal_i2c::{
+    AddressMode, AnyAddress, AsyncI2cController, AsyncI2cTarget, ErrorKind, ErrorType,
+    NoAcknowledgeSource, Operation, Transaction,
+};
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::mpsc::{Sender, channel};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Largest frame accepted, to not allocate arbitrary amounts of memory for a corrupt length.
+const MAX_FRAME: usize = 1 << 20;
+
+/// Controller sending its transactions to a [`TcpTarget`] over TCP
+pub struct TcpController {
+    stream: TcpStream,
+}
+
+impl TcpController {
+    /// Connect to the [`TcpTarget`] listening on `address`.
+    pub async fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self::new(TcpStream::connect(address).await?))
+    }
+
+    /// Use the connection `stream` to a [`TcpTarget`].
+    pub const fn new(stream: TcpStream) -> Self {
+        Self { stream }
+    }
+
+    async fn exchange(&mut self, transaction: &SimTransaction) -> io::Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        transaction.encode(&mut payload);
+        write_frame(&mut self.stream, &payload).await?;
+        read_frame(&mut self.stream)
+            .await?
+            .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+}
+
+impl ErrorType for TcpController {
+    type Error = ErrorKind;
+}
+
+impl<A> AsyncI2cController<A> for TcpController
+where
+    A: AddressMode + Into<AnyAddress>,
+{
+    async fn transaction(
+        &mut self,
+        address: A,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let address = address.into();
+        let actions = operations
+            .iter()
+            .map(|op| match op {
+                Operation::Read(buf) => (address, SimOp::Read(vec![0; buf.len()])),
+                Operation::Write(data) => (address, SimOp::Write(data.to_vec())),
+            })
+            .collect();
+        let transaction = SimTransaction {
+            address,
+            actions,
+            clock_hz: None,
+            max_stretch: None,
+            context: None,
+        };
+
+        let reply = self
+            .exchange(&transaction)
+            .await
+            .map_err(|_| ErrorKind::Other)?;
+        let done = decode_reply(&reply).ok_or(ErrorKind::Other)??;
+        if done.actions.len() != operations.len() {
+            return Err(ErrorKind::Other);
+        }
+        for (op, (_, reply)) in operations.iter_mut().zip(done.actions) {
+            if let (Operation::Read(buf), SimOp::Read(data)) = (op, reply) {
+                if buf.len() != data.len() {
+                    return Err(ErrorKind::Other);
+                }
+                buf.copy_from_slice(&data);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Target serving the transactions of a [`TcpController`] on the other end of a TCP connection
+///
+/// The transactions received are served by a [`SimTarget`], which can be configured through
+/// [`Self::sim`]. A task spawned on the tokio runtime forwards them from the connection. Once the
+/// connection is closed, `listen` returns [`ErrorKind::Other`], like it does on a `SimTarget`
+/// once all controllers are dropped.
+pub struct TcpTarget {
+    target: SimTarget,
+    bridge: JoinHandle<()>,
+}
+
+impl TcpTarget {
+    /// Serve the transactions received on `stream`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn new(stream: TcpStream) -> Self {
+        let (to_target, from_controller) = channel(1);
+        Self {
+            target: SimTarget::new(from_controller),
+            bridge: tokio::spawn(async move {
+                let _ = bridge(stream, to_target).await;
+            }),
+        }
+    }
+
+    /// The simulated target serving the transactions, to configure it.
+    pub const fn sim(&mut self) -> &mut SimTarget {
+        &mut self.target
+    }
+}
+
+impl Drop for TcpTarget {
+    fn drop(&mut self) {
+        self.bridge.abort();
+    }
+}
+
+impl AsyncI2cTarget for TcpTarget {
+    type Error = ErrorKind;
+    type Read<'a> = <SimTarget as AsyncI2cTarget>::Read<'a>;
+    type Write<'a> = <SimTarget as AsyncI2cTarget>::Write<'a>;
+
+    async fn listen(
+        &mut self,
+    ) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        AsyncI2cTarget::listen(&mut self.target).await
+    }
+
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        AsyncI2cTarget::addresses(&self.target)
+    }
+
+    fn ops_remaining(&self) -> Option<usize> {
+        AsyncI2cTarget::ops_remaining(&self.target)
+    }
+
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        AsyncI2cTarget::clock_range(&self.target)
+    }
+
+    fn take_reset(&mut self) -> bool {
+        AsyncI2cTarget::take_reset(&mut self.target)
+    }
+}
+
+/// Pass the transactions received on `stream` to the target, and send back its replies.
+async fn bridge(mut stream: TcpStream, to_target: Sender<PartialTransaction>) -> io::Result<()> {
+    while let Some(payload) = read_frame(&mut stream).await? {
+        let transaction = SimTransaction::decode(&mut Reader(&payload))
+            .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?;
+        let (responder, reply) = oneshot::channel();
+        if to_target
+            .send(PartialTransaction::new(transaction, responder))
+            .await
+            .is_err()
+        {
+            return Ok(());
+        }
+        let reply = reply.await.unwrap_or(Err(ErrorKind::Other));
+        write_frame(&mut stream, &encode_reply(&reply)).await?;
+    }
+    Ok(())
+}
+
+/// Read a frame, or `None` if the connection was closed before it.
+async fn read_frame(stream: &mut TcpStream) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    match stream.read_exact(&mut len).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME {
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload).await?;
+    Ok(Some(payload))
+}
+
+async fn write_frame(stream: &mut TcpStream, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len()).map_err(|_| io::ErrorKind::InvalidInput)?;
+    stream.write_all(&len.to_be_bytes()).await?;
+    stream.write_all(payload).await
+}
+
+fn encode_reply(reply: &Result<SimTransaction, ErrorKind>) -> Vec<u8> {
+    let mut out = Vec::new();
+    match reply {
+        Ok(transaction) => {
+            out.push(0);
+            transaction.encode(&mut out);
+        }
+        Err(kind) => {
+            out.push(1);
+            out.push(match kind {
+                ErrorKind::Bus => 0,
+                ErrorKind::ArbitrationLoss => 1,
+                ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address) => 2,
+                ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data) => 3,
+                ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown) => 4,
+                ErrorKind::Overrun => 5,
+                _ => 6,
+            });
+        }
+    }
+    out
+}
+
+/// Decode a reply, or `None` if it is malformed.
+fn decode_reply(payload: &[u8]) -> Option<Result<SimTransaction, ErrorKind>> {
+    let mut reader = Reader(payload);
+    Some(match reader.u8()? {
+        0 => Ok(SimTransaction::decode(&mut reader)?),
+        1 => Err(match reader.u8()? {
+            0 => ErrorKind::Bus,
+            1 => ErrorKind::ArbitrationLoss,
+            2 => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            3 => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+            4 => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            5 => ErrorKind::Overrun,
+            _ => ErrorKind::Other,
+        }),
+        _ => return None,
+    })
+}
+
+/// Cursor over a payload being decoded
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> Option<&[u8]> {
+        if len > self.0.len() {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_be_bytes(self.bytes(8)?.try_into().ok()?))
+    }
+
+    fn address(&mut self) -> Option<AnyAddress> {
+        match (self.u8()?, self.u16()?) {
+            (0, address) => Some(AnyAddress::Seven(u8::try_from(address).ok()?)),
+            (1, address) => Some(AnyAddress::Ten(address)),
+            _ => None,
+        }
+    }
+}
+
+fn encode_address(address: AnyAddress, out: &mut Vec<u8>) {
+    let (tag, address) = match address {
+        AnyAddress::Seven(address) => (0, u16::from(address)),
+        AnyAddress::Ten(address) => (1, address),
+    };
+    out.push(tag);
+    out.extend_from_slice(&address.to_be_bytes());
+}
+
+impl SimTransaction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_address(self.address, out);
+        match self.clock_hz {
+            Some(hz) => {
+                out.push(1);
+                out.extend_from_slice(&hz.to_be_bytes());
+            }
+            None => out.push(0),
+        }
+        match self.max_stretch {
+            Some(max) => {
+                out.push(1);
+                let nanos = u64::try_from(max.as_nanos()).unwrap_or(u64::MAX);
+                out.extend_from_slice(&nanos.to_be_bytes());
+            }
+            None => out.push(0),
+        }
+        match self.context {
+            Some(context) => {
+                out.push(1);
+                out.extend_from_slice(&context.to_be_bytes());
+            }
+            None => out.push(0),
+        }
+        out.extend_from_slice(&(self.actions.len() as u32).to_be_bytes());
+        for (address, op) in &self.actions {
+            encode_address(*address, out);
+            let (direction, data) = match op {
+                SimOp::Write(data) => (0, data),
+                SimOp::Read(data) => (1, data),
+            };
+            out.push(direction);
+            out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            out.extend_from_slice(data);
+        }
+    }
+
+    /// Decode a transaction, or `None` if it is malformed.
+    fn decode(reader: &mut Reader<'_>) -> Option<Self> {
+        let address = reader.address()?;
+        let clock_hz = match reader.u8()? {
+            0 => None,
+            _ => Some(reader.u32()?),
+        };
+        let max_stretch = match reader.u8()? {
+            0 => None,
+            _ => Some(Duration::from_nanos(reader.u64()?)),
+        };
+        let context = match reader.u8()? {
+            0 => None,
+            _ => Some(reader.u64()?),
+        };
+        let count = reader.u32()?;
+        let mut actions = Vec::new();
+        for _ in 0..count {
+            let address = reader.address()?;
+            let direction = reader.u8()?;
+            let len = reader.u32()? as usize;
+            let data = reader.bytes(len)?.to_vec();
+            let op = match direction {
+                0 => SimOp::Write(data),
+                1 => SimOp::Read(data),
+                _ => return None,
+            };
+            actions.push((address, op));
+        }
+        Some(Self {
+            address,
+            actions,
+            clock_hz,
+            max_stretch,
+            context,
+        })
+    }
+}
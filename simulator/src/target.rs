@@ -1,12 +1,50 @@
 //! Implementation of the target half of the simulator
 
-use crate::{PartialTransaction, SimOp};
+use crate::{PartialTransaction, SimOp, SimTransaction};
+use embedded_hal_i2c::controller_ext::{DEVICE_ID_ADDRESS, DeviceId};
 use embedded_hal_i2c::{
-    AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction, ErrorKind, NoAcknowledgeSource,
-    ReadResult, Transaction, WriteResult,
+    AnyAddress, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction, ErrorKind,
+    NoAcknowledgeSource, ReadResult, SyncI2cTarget, SyncReadTransaction, SyncWriteTransaction,
+    Transaction, WriteResult,
 };
 use std::cmp::min;
-use tokio::sync::mpsc::Receiver;
+use std::fmt::{self, Write as _};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::sync::mpsc::{Receiver, UnboundedReceiver, UnboundedSender, unbounded_channel};
+
+/// Statistics collected by a [`SimTarget`] over its lifetime
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SimStats {
+    /// Number of transactions that reached the target, whether acknowledged or not
+    pub transactions: usize,
+    /// Number of transactions where the address was not acknowledged
+    pub address_naks: usize,
+    /// Number of transactions where a data byte was not acknowledged
+    pub data_naks: usize,
+    /// Number of transactions not acknowledged for another reason, e.g. a power cycle
+    pub unknown_naks: usize,
+    /// XOR of all bytes the controller read, including overrun characters
+    pub read_checksum: u8,
+    /// XOR of all bytes the target received
+    pub write_checksum: u8,
+}
+
+impl SimStats {
+    fn record_nak(&mut self, src: NoAcknowledgeSource) {
+        match src {
+            NoAcknowledgeSource::Address => self.address_naks += 1,
+            NoAcknowledgeSource::Data => self.data_naks += 1,
+            NoAcknowledgeSource::Unknown => self.unknown_naks += 1,
+        }
+    }
+
+    fn checksum(bytes: &[u8]) -> u8 {
+        bytes.iter().fold(0, |sum, byte| sum ^ byte)
+    }
+}
 
 /// Simulated I2C target
 ///
@@ -14,10 +52,131 @@ use tokio::sync::mpsc::Receiver;
 /// All [`AsyncI2cTarget::listen`], [`AsyncReadTransaction::handle_part`],
 /// and [`AsyncWriteTransaction::handle_part`] calls on this target are forwarded
 /// to back to the controller as if there was a real I2C bus connecting the two.
+/// The target can also be used synchronously through [`SyncI2cTarget`].
+///
+/// Each [`Operation`](embedded_hal_i2c::Operation) in a controller transaction is reported as a
+/// separate [`Transaction`], as if a repeated start was used between them. The direction of the
+/// reported transaction matches the R/W bit the controller would put in the address byte: an
+/// `Operation::Write` is always reported as [`Transaction::Write`], and an `Operation::Read` as
+/// [`Transaction::Read`], also when both go to the same address.
+///
+/// Operations without any data are reported too. The handler of an empty read completes with 0
+/// bytes on the first non-empty `handle_part`, like that of an empty write does.
 pub struct SimTarget {
     current_transaction: Option<PartialTransaction>,
     from_controller: Receiver<PartialTransaction>,
     need_to_report_deselect: bool,
+    address_mask: Option<(AnyAddress, u16)>,
+    general_call: bool,
+    stats: SimStats,
+    steps: Option<UnboundedReceiver<()>>,
+    step_ready: bool,
+    ack_policy: Option<AckPolicy>,
+    data_ready: Option<Arc<AtomicBool>>,
+    strict_accept: bool,
+    strict_deselect: bool,
+    transcript: Option<String>,
+    observer: Option<Box<dyn SimObserver>>,
+    clock_range: Option<(u32, u32)>,
+    read_stretch: Duration,
+    write_stretch: Duration,
+    over_read: OverRead,
+    echo: Option<Vec<u8>>,
+    device_id: Option<DeviceId>,
+    reset_signal: Option<Arc<AtomicBool>>,
+    reset_pending: bool,
+}
+
+type AckPolicy = Box<dyn Fn(AnyAddress, Direction) -> bool + Send>;
+
+/// State of a [`SimTarget`] taken with [`SimTarget::snapshot`], to be restored later
+///
+/// This covers the bus state, statistics, transcript and the settings that are plain values. The
+/// ack policy and observer are closures, so they are not part of it and left as they are on
+/// restore. The data ready flag is shared, so only which flag is used is restored, not its value.
+#[derive(Debug, Clone)]
+pub struct SimSnapshot {
+    need_to_report_deselect: bool,
+    address_mask: Option<(AnyAddress, u16)>,
+    general_call: bool,
+    stats: SimStats,
+    data_ready: Option<Arc<AtomicBool>>,
+    strict_accept: bool,
+    strict_deselect: bool,
+    transcript: Option<String>,
+    clock_range: Option<(u32, u32)>,
+    read_stretch: Duration,
+    write_stretch: Duration,
+    over_read: OverRead,
+    echo: Option<Vec<u8>>,
+    device_id: Option<DeviceId>,
+    reset_signal: Option<Arc<AtomicBool>>,
+    reset_pending: bool,
+}
+
+/// Callbacks for the lifecycle of the handlers of a [`SimTarget`], set with
+/// [`SimTarget::set_observer`]
+///
+/// All methods do nothing by default, so only the events of interest need to be implemented.
+pub trait SimObserver: Send {
+    /// The handler acknowledged its address.
+    fn on_address_ack(&mut self, address: AnyAddress, direction: Direction) {
+        let _ = (address, direction);
+    }
+
+    /// `len` data bytes were transferred and acknowledged.
+    fn on_data_ack(&mut self, len: usize) {
+        let _ = len;
+    }
+
+    /// The transaction was not acknowledged, ending it.
+    fn on_nack(&mut self, source: NoAcknowledgeSource) {
+        let _ = source;
+    }
+
+    /// The handler completed its operation after transferring `size` bytes, including any overrun.
+    fn on_complete(&mut self, size: usize) {
+        let _ = size;
+    }
+
+    /// A controller transaction to `address` reached the target, with the `context` it was tagged
+    /// with through
+    /// [`SimController::transaction_tagged`](crate::controller::SimController::transaction_tagged).
+    fn on_transaction(&mut self, address: AnyAddress, context: Option<u64>) {
+        let _ = (address, context);
+    }
+}
+
+/// Direction of a transaction, as indicated by the R/W bit after the address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The controller reads from the target
+    Read,
+    /// The controller writes to the target
+    Write,
+}
+
+/// What the controller reads past the data a [`SimTarget`] provided, set with
+/// [`SimTarget::set_over_read`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverRead {
+    /// The fill byte `0x2a`, which stands out in test output
+    #[default]
+    Fill,
+    /// The idle level of the bus, like a device that stops driving the bus, e.g. `0xFF` with
+    /// pull-ups
+    Idle(u8),
+}
+
+impl OverRead {
+    const FILL: u8 = 0x2a;
+
+    const fn byte(self) -> u8 {
+        match self {
+            Self::Fill => Self::FILL,
+            Self::Idle(level) => level,
+        }
+    }
 }
 
 impl SimTarget {
@@ -26,9 +185,458 @@ impl SimTarget {
             current_transaction: None,
             from_controller,
             need_to_report_deselect: false,
+            address_mask: None,
+            general_call: false,
+            stats: SimStats {
+                transactions: 0,
+                address_naks: 0,
+                data_naks: 0,
+                unknown_naks: 0,
+                read_checksum: 0,
+                write_checksum: 0,
+            },
+            steps: None,
+            step_ready: false,
+            ack_policy: None,
+            data_ready: None,
+            strict_accept: false,
+            strict_deselect: false,
+            transcript: None,
+            observer: None,
+            clock_range: None,
+            read_stretch: Duration::ZERO,
+            write_stretch: Duration::ZERO,
+            over_read: OverRead::Fill,
+            echo: None,
+            device_id: None,
+            reset_signal: None,
+            reset_pending: false,
         }
     }
 
+    /// Whether a controller transaction is waiting for `listen`, or is in progress.
+    ///
+    /// Useful to find out why a test hangs: a pending transaction with the target not listening
+    /// stalls the controller.
+    pub fn has_pending(&self) -> bool {
+        self.current_transaction.is_some() || !self.from_controller.is_empty()
+    }
+
+    /// Address of the transaction in progress, if any.
+    ///
+    /// The address of a transaction still waiting in the queue is not known until `listen` takes
+    /// it, so this is `None` even if [`Self::has_pending`] is true.
+    pub fn pending_address(&self) -> Option<AnyAddress> {
+        self.current_transaction
+            .as_ref()
+            .map(|t| t.transaction.address)
+    }
+
+    /// Whether `listen` handed out a handler for a controller transaction that has not finished
+    /// yet, e.g. because the handler was leaked, or between the operations of a `write_read`.
+    ///
+    /// Dropping the target now would abort the transfer. Unlike [`Self::has_pending`], this does
+    /// not include transactions still waiting for `listen`.
+    pub fn in_transaction(&self) -> bool {
+        self.current_transaction
+            .as_ref()
+            .is_some_and(|t| t.current_op < t.transaction.actions.len())
+    }
+
+    /// Statistics collected since the target was created or [`Self::reset_stats`] was called.
+    pub const fn stats(&self) -> SimStats {
+        self.stats
+    }
+
+    /// Assert that exactly `count` transactions reached the target, as counted in
+    /// [`SimStats::transactions`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of transactions differs.
+    #[track_caller]
+    pub fn assert_transaction_count(&self, count: usize) {
+        assert_eq!(
+            self.stats.transactions, count,
+            "target saw {} transactions, expected {count}",
+            self.stats.transactions
+        );
+    }
+
+    /// Reset all statistics to zero.
+    pub fn reset_stats(&mut self) {
+        self.stats = SimStats::default();
+    }
+
+    /// Only respond to addresses that match `base` on all bits set in `mask`.
+    ///
+    /// Transactions for other addresses are not acknowledged, and never reported by `listen`.
+    /// Matching transactions report the exact address used by the controller, so the bits outside
+    /// of the mask can be used to select between multiple functions of the device.
+    pub fn set_address_mask(&mut self, base: AnyAddress, mask: u16) {
+        self.address_mask = Some((base, mask));
+    }
+
+    /// Only respond to `address`.
+    pub fn set_address(&mut self, address: AnyAddress) {
+        self.set_address_mask(address, u16::MAX);
+    }
+
+    /// Respond to all addresses again, undoing [`Self::set_address_mask`].
+    pub fn clear_address_mask(&mut self) {
+        self.address_mask = None;
+    }
+
+    /// All addresses this target responds to, or none if it responds to any address.
+    fn matching_addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        let candidates = match self.address_mask {
+            None => 0..0,
+            Some((AnyAddress::Seven(_), _)) => 0..0x80,
+            Some((AnyAddress::Ten(_), _)) => 0..0x400,
+        };
+        candidates
+            .map(|address| match self.address_mask {
+                Some((AnyAddress::Seven(_), _)) => AnyAddress::Seven(address as u8),
+                _ => AnyAddress::Ten(address),
+            })
+            .filter(|&address| self.matches(address))
+            // A seven bit general call is already covered above
+            .chain(
+                (self.general_call && matches!(self.address_mask, Some((AnyAddress::Ten(_), _))))
+                    .then_some(AnyAddress::Seven(0)),
+            )
+    }
+
+    /// Also respond to the general call address (0x00) when an address mask is set.
+    ///
+    /// Like the general call enable bit of a hardware target, this is off by default.
+    pub fn set_general_call(&mut self, enabled: bool) {
+        self.general_call = enabled;
+    }
+
+    fn matches(&self, address: AnyAddress) -> bool {
+        if self.general_call && address == AnyAddress::Seven(0) {
+            return true;
+        }
+        match (self.address_mask, address) {
+            (None, _) => true,
+            (Some((AnyAddress::Seven(base), mask)), AnyAddress::Seven(address)) => {
+                u16::from(address ^ base) & mask == 0
+            }
+            (Some((AnyAddress::Ten(base), mask)), AnyAddress::Ten(address)) => {
+                (address ^ base) & mask == 0
+            }
+            (Some(_), _) => false,
+        }
+    }
+
+    /// Declare the lowest and highest bus clock the target supports, in Hz.
+    ///
+    /// This is reported by `clock_range`. Transactions from a controller with a bus clock set
+    /// through [`SimController::set_clock`](crate::controller::SimController::set_clock) outside of
+    /// this range are not acknowledged, so the controller gets
+    /// [`ErrorKind::NoAcknowledge`] with [`NoAcknowledgeSource::Address`], like a device that
+    /// cannot keep up misses its address. `None` accepts any clock.
+    pub fn set_clock_range(&mut self, range: Option<(u32, u32)>) {
+        self.clock_range = range;
+    }
+
+    /// Stretch the clock for every byte the target provides or receives, for timing tests.
+    ///
+    /// Every `handle_part` call on a handler takes `read_per_byte` per byte provided by an
+    /// [`OnRead`], or `write_per_byte` per byte received by an [`OnWrite`], before the data is
+    /// transferred. This models devices that are slower in one direction than in the other. Both
+    /// are zero by default.
+    ///
+    /// A controller limiting the stretch with
+    /// [`SimController::set_max_stretch`](crate::controller::SimController::set_max_stretch)
+    /// aborts the transaction once a byte takes too long. The handler then returns
+    /// [`ErrorKind::Bus`].
+    pub fn set_stretch(&mut self, read_per_byte: Duration, write_per_byte: Duration) {
+        self.read_stretch = read_per_byte;
+        self.write_stretch = write_per_byte;
+    }
+
+    /// Choose what the controller reads once a read handler stopped providing data, i.e. after it
+    /// was dropped.
+    ///
+    /// A target cannot end a read on I2C, the controller does. This sets the bytes it gets
+    /// instead. [`OverRead::Fill`] by default.
+    pub fn set_over_read(&mut self, over_read: OverRead) {
+        self.over_read = over_read;
+    }
+
+    /// Answer reads with the data of the last write, for loopback diagnostics.
+    ///
+    /// With this enabled, the target handles reads itself: they are not reported by `listen`, but
+    /// answered with the bytes received by the last write, followed by the over-read bytes set
+    /// with [`Self::set_over_read`]. Reads refused by the ack policy or data ready gate are still
+    /// reported, to be refused by the handler as usual. Disabling echo mode forgets the last
+    /// write.
+    pub fn set_echo(&mut self, enabled: bool) {
+        self.echo = enabled.then(Vec::new);
+    }
+
+    /// Answer the device ID command for the addresses of the target with `id`, or do not support
+    /// the command if `None`, the default.
+    ///
+    /// The target handles device ID reads through [`DEVICE_ID_ADDRESS`] itself, they are not
+    /// reported by `listen`. A read longer than three bytes starts over at the first byte.
+    /// Commands naming an address the target does not respond to are not acknowledged.
+    pub const fn set_device_id(&mut self, id: Option<DeviceId>) {
+        self.device_id = id;
+    }
+
+    fn supports_clock(&self, clock_hz: Option<u32>) -> bool {
+        match (self.clock_range, clock_hz) {
+            (Some((min, max)), Some(hz)) => (min..=max).contains(&hz),
+            _ => true,
+        }
+    }
+
+    /// Simulate a power-on reset of the target device.
+    ///
+    /// Any transaction in progress is aborted and not acknowledged, after which the next `listen`
+    /// reports [`Transaction::Deselect`]. With [`Self::set_strict_deselect`], an idle target
+    /// reports no deselect, as nothing happened on the bus. Either way `take_reset` returns `true`
+    /// once, so the service running on the target can clear its state like the device would.
+    pub fn power_cycle(&mut self) {
+        let aborted = self.current_transaction.is_some();
+        if let Some(t) = self.current_transaction.take() {
+            println!("Power cycle aborted transaction: {:?}", t.transaction);
+            self.note(format_args!(
+                "outcome: NAK {:?} (power cycle)",
+                NoAcknowledgeSource::Unknown
+            ));
+            self.stats.record_nak(NoAcknowledgeSource::Unknown);
+            let _ = t
+                .responder
+                .send(Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown)));
+        }
+        self.need_to_report_deselect = aborted || !self.strict_deselect;
+        self.reset_pending = true;
+    }
+
+    /// Power cycle the target when `reset` is set, from outside the service that owns the target.
+    ///
+    /// The flag is checked, and cleared, whenever a transaction arrives from the controller. The
+    /// power cycle then happens before that transaction is handled, as with [`Self::power_cycle`].
+    pub fn set_reset_signal(&mut self, reset: Option<Arc<AtomicBool>>) {
+        self.reset_signal = reset;
+    }
+
+    /// Capture the state of the target, to return to it later with [`Self::restore`], e.g. to run
+    /// several sequences of a property test from the same starting point.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a transaction is in progress, as it cannot be duplicated. Take snapshots when
+    /// `listen` returned [`Transaction::Deselect`] or before the first transaction.
+    pub fn snapshot(&self) -> SimSnapshot {
+        assert!(
+            self.current_transaction.is_none(),
+            "cannot snapshot a target during a transaction"
+        );
+        SimSnapshot {
+            need_to_report_deselect: self.need_to_report_deselect,
+            address_mask: self.address_mask,
+            general_call: self.general_call,
+            stats: self.stats,
+            data_ready: self.data_ready.clone(),
+            strict_accept: self.strict_accept,
+            strict_deselect: self.strict_deselect,
+            transcript: self.transcript.clone(),
+            clock_range: self.clock_range,
+            read_stretch: self.read_stretch,
+            write_stretch: self.write_stretch,
+            over_read: self.over_read,
+            echo: self.echo.clone(),
+            device_id: self.device_id,
+            reset_signal: self.reset_signal.clone(),
+            reset_pending: self.reset_pending,
+        }
+    }
+
+    /// Return to the state captured by [`Self::snapshot`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a transaction is in progress, like [`Self::snapshot`]. Use
+    /// [`Self::power_cycle`] first to abort it.
+    pub fn restore(&mut self, snapshot: SimSnapshot) {
+        assert!(
+            self.current_transaction.is_none(),
+            "cannot restore a target during a transaction"
+        );
+        let SimSnapshot {
+            need_to_report_deselect,
+            address_mask,
+            general_call,
+            stats,
+            data_ready,
+            strict_accept,
+            strict_deselect,
+            transcript,
+            clock_range,
+            read_stretch,
+            write_stretch,
+            over_read,
+            echo,
+            device_id,
+            reset_signal,
+            reset_pending,
+        } = snapshot;
+        self.need_to_report_deselect = need_to_report_deselect;
+        self.address_mask = address_mask;
+        self.general_call = general_call;
+        self.stats = stats;
+        self.data_ready = data_ready;
+        self.strict_accept = strict_accept;
+        self.strict_deselect = strict_deselect;
+        self.transcript = transcript;
+        self.clock_range = clock_range;
+        self.read_stretch = read_stretch;
+        self.write_stretch = write_stretch;
+        self.over_read = over_read;
+        self.echo = echo;
+        self.device_id = device_id;
+        self.reset_signal = reset_signal;
+        self.reset_pending = reset_pending;
+    }
+
+    /// Decide per address and direction whether to acknowledge the address, for negative testing.
+    ///
+    /// Transactions for which `policy` returns false are still reported by `listen`, but the
+    /// handler does not acknowledge the address on first use: `handle_part` returns
+    /// [`ErrorKind::NoAcknowledge`] instead. This comes on top of [`Self::set_address_mask`],
+    /// addresses outside of the mask are never reported.
+    pub fn set_ack_policy(
+        &mut self,
+        policy: impl Fn(AnyAddress, Direction) -> bool + Send + 'static,
+    ) {
+        self.ack_policy = Some(Box::new(policy));
+    }
+
+    /// Acknowledge every address that is reported again, undoing [`Self::set_ack_policy`].
+    pub fn clear_ack_policy(&mut self) {
+        self.ack_policy = None;
+    }
+
+    /// Only acknowledge reads while `ready` is set, or always if `None`, like a sensor that does not
+    /// acknowledge its address until a conversion finished.
+    ///
+    /// Refused reads are reported like with [`Self::set_ack_policy`]. Writes are not affected, so
+    /// the application can clear `ready` when a write starts a conversion, and set it once the
+    /// conversion time has passed.
+    pub fn set_data_ready(&mut self, ready: Option<Arc<AtomicBool>>) {
+        self.data_ready = ready;
+    }
+
+    /// Require handlers to acknowledge the address explicitly.
+    ///
+    /// With this enabled, the address is only acknowledged by calling `accept` on the
+    /// [`OnRead`] or [`OnWrite`] handler. Using the handler without accepting it first does not
+    /// acknowledge the address: `handle_part` returns [`ErrorKind::NoAcknowledge`] instead.
+    pub fn set_strict_accept(&mut self, enabled: bool) {
+        self.strict_accept = enabled;
+    }
+
+    /// Only report [`Transaction::Deselect`] when the target was actually selected.
+    ///
+    /// By default, a transaction of which the address is not acknowledged is followed by a
+    /// deselect, which the trait allows. With this enabled, that deselect is left out, so drivers
+    /// can be checked not to rely on it with [`Self::expect_no_spurious_deselect`]. A transaction
+    /// ending after an acknowledged operation is still followed by a deselect.
+    pub fn set_strict_deselect(&mut self, enabled: bool) {
+        self.strict_deselect = enabled;
+    }
+
+    /// Assert that `listen` will not report a deselect next.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a deselect is pending, e.g. after an address NAK without
+    /// [`Self::set_strict_deselect`].
+    #[track_caller]
+    pub fn expect_no_spurious_deselect(&self) {
+        assert!(
+            !self.need_to_report_deselect,
+            "listen would report a deselect next"
+        );
+    }
+
+    /// Call `observer` on every step in the lifecycle of the handlers, for protocol debugging.
+    pub fn set_observer(&mut self, observer: impl SimObserver + 'static) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    /// Stop calling the observer set with [`Self::set_observer`].
+    pub fn clear_observer(&mut self) {
+        self.observer = None;
+    }
+
+    fn observe(&mut self, event: impl FnOnce(&mut dyn SimObserver)) {
+        if let Some(observer) = &mut self.observer {
+            event(observer.as_mut());
+        }
+    }
+
+    /// Start recording a transcript of all transactions, discarding any earlier transcript.
+    ///
+    /// For every transaction the transcript shows what the controller requested, how the target
+    /// handled it byte by byte, and the outcome the controller got.
+    pub fn record_transcript(&mut self) {
+        self.transcript = Some(String::new());
+    }
+
+    /// The transcript recorded since [`Self::record_transcript`], or an empty string if not
+    /// recording.
+    pub fn transcript(&self) -> &str {
+        self.transcript.as_deref().unwrap_or_default()
+    }
+
+    fn note(&mut self, args: fmt::Arguments) {
+        if let Some(transcript) = &mut self.transcript {
+            // Writing to a String cannot fail
+            let _ = transcript.write_fmt(args);
+            transcript.push('\n');
+        }
+    }
+
+    fn note_request(&mut self, transaction: &SimTransaction) {
+        match transaction.context {
+            Some(context) => self.note(format_args!(
+                "controller: transaction to {:?}, context {context}",
+                transaction.address
+            )),
+            None => self.note(format_args!(
+                "controller: transaction to {:?}",
+                transaction.address
+            )),
+        }
+        for (address, op) in &transaction.actions {
+            match op {
+                SimOp::Write(data) => self.note(format_args!("  {address:?} write {data:02x?}")),
+                SimOp::Read(buf) => {
+                    self.note(format_args!("  {address:?} read {} bytes", buf.len()))
+                }
+            }
+        }
+    }
+
+    /// Pause the target until told to continue, for step-debugging.
+    ///
+    /// From now on, every transaction from the controller is only let through to the target after
+    /// a call to [`SimStepper::step`] on the returned handle. The controller stays blocked until
+    /// then, so the state of the application can be inspected in between transactions. Dropping
+    /// the handle lets the target run freely again.
+    pub fn step_mode(&mut self) -> SimStepper {
+        let (steps, from_stepper) = unbounded_channel();
+        self.steps = Some(from_stepper);
+        self.step_ready = false;
+        SimStepper { steps }
+    }
+
     fn nak(&mut self, src: NoAcknowledgeSource) {
         let t = self
             .current_transaction
@@ -36,84 +644,441 @@ impl SimTarget {
             .expect("Can only be done with error if there is a transaction");
 
         println!("NAK transaction: {src:?}");
+        self.note(format_args!("outcome: NAK {src:?}"));
+        self.stats.record_nak(src);
+        self.observe(|o| o.on_nack(src));
         assert!(!self.need_to_report_deselect);
-        self.need_to_report_deselect = true;
+        // Without any acknowledged operation, the target was never selected
+        let selected = src != NoAcknowledgeSource::Address || t.current_op > 0;
+        self.need_to_report_deselect = selected || !self.strict_deselect;
 
         let _ = t.responder.send(Err(ErrorKind::NoAcknowledge(src)));
     }
 
+    /// End the current transaction because the controller gave up waiting for a clock stretch.
+    fn abort(&mut self) {
+        let t = self
+            .current_transaction
+            .take()
+            .expect("Can only abort if there is a transaction");
+
+        println!("Controller aborted transaction");
+        self.note(format_args!("outcome: controller aborted on clock stretch"));
+        assert!(!self.need_to_report_deselect);
+        self.need_to_report_deselect = true;
+
+        let _ = t.responder.send(Err(ErrorKind::Other));
+    }
+
+    /// Time to stretch the clock for `bytes` bytes, taking `per_byte` each, or `Err` with the time
+    /// after which the controller aborts the current transaction.
+    fn stretch(&self, per_byte: Duration, bytes: usize) -> Result<Duration, Duration> {
+        let max = self
+            .current_transaction
+            .as_ref()
+            .and_then(|t| t.transaction.max_stretch);
+        match max {
+            Some(max) if bytes > 0 && per_byte > max => Err(max),
+            _ => Ok(per_byte.saturating_mul(u32::try_from(bytes).unwrap_or(u32::MAX))),
+        }
+    }
+
     fn next(&mut self) {
         let inner = self
             .current_transaction
             .as_mut()
             .expect("Can only be done with error if there is a transaction");
         inner.current_op += 1;
+        inner.reported_restart = false;
     }
 }
 
-impl AsyncI2cTarget for SimTarget {
-    type Error = ErrorKind;
-    type Read<'a> = OnRead<'a>;
-    type Write<'a> = OnWrite<'a>;
+impl SimTarget {
+    fn needs_transaction(&self) -> bool {
+        !self.need_to_report_deselect && self.current_transaction.is_none()
+    }
 
-    async fn listen(
-        &mut self,
-    ) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+    /// Number of operations of the current controller transaction that have not finished yet.
+    ///
+    /// This is an oracle only the simulator has: the controller announces the whole transaction
+    /// up front, which a real bus does not.
+    fn current_ops_remaining(&self) -> Option<usize> {
+        self.current_transaction
+            .as_ref()
+            .map(|t| t.transaction.actions.len() - t.current_op)
+    }
+
+    /// Whether a new transaction may be received, without waiting for a step.
+    fn try_step(&mut self) -> bool {
+        if let Some(steps) = &mut self.steps
+            && !self.step_ready
+        {
+            match steps.try_recv() {
+                Ok(()) => self.step_ready = true,
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => self.steps = None,
+            }
+        }
+        true
+    }
+
+    async fn wait_for_step(&mut self) {
+        if let Some(steps) = &mut self.steps
+            && !self.step_ready
+        {
+            match steps.recv().await {
+                Some(()) => self.step_ready = true,
+                None => self.steps = None,
+            }
+        }
+    }
+
+    fn blocking_wait_for_step(&mut self) {
+        if let Some(steps) = &mut self.steps
+            && !self.step_ready
+        {
+            match steps.blocking_recv() {
+                Some(()) => self.step_ready = true,
+                None => self.steps = None,
+            }
+        }
+    }
+
+    fn receive(&mut self, new: PartialTransaction) {
+        if self
+            .reset_signal
+            .as_ref()
+            .is_some_and(|reset| reset.swap(false, Ordering::Relaxed))
+        {
+            self.power_cycle();
+        }
+        self.step_ready = false;
+        self.stats.transactions += 1;
+        let (address, context) = (new.transaction.address, new.transaction.context);
+        self.observe(|o| o.on_transaction(address, context));
+        if let Some(id) = self.device_id
+            && new.transaction.address == AnyAddress::Seven(DEVICE_ID_ADDRESS)
+        {
+            self.answer_device_id(new, id);
+            return;
+        }
+        if !self.matches(new.transaction.address) || !self.supports_clock(new.transaction.clock_hz)
+        {
+            println!("Ignored transaction: {:?}", new.transaction);
+            self.note_request(&new.transaction);
+            self.note(format_args!(
+                "outcome: NAK {:?}",
+                NoAcknowledgeSource::Address
+            ));
+            self.stats.record_nak(NoAcknowledgeSource::Address);
+            self.observe(|o| o.on_nack(NoAcknowledgeSource::Address));
+            let _ = new
+                .responder
+                .send(Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)));
+            return;
+        }
+        println!("New transaction: {:?}", new.transaction);
+        self.note_request(&new.transaction);
+        self.current_transaction = Some(new);
+    }
+
+    /// Handle a device ID command in `new` without involving `listen`.
+    fn answer_device_id(&mut self, mut new: PartialTransaction, id: DeviceId) {
+        println!("Device ID transaction: {:?}", new.transaction);
+        self.note_request(&new.transaction);
+        let selected = match new.transaction.actions.first() {
+            Some((_, SimOp::Write(data))) => data
+                .first()
+                .is_some_and(|byte| self.matches(AnyAddress::Seven(byte >> 1))),
+            _ => false,
+        };
+        if !selected {
+            self.note(format_args!(
+                "outcome: NAK {:?} (device ID)",
+                NoAcknowledgeSource::Data
+            ));
+            self.stats.record_nak(NoAcknowledgeSource::Data);
+            let _ = new
+                .responder
+                .send(Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data)));
+            return;
+        }
+
+        let bytes = id.to_bytes();
+        for (_, op) in &mut new.transaction.actions {
+            if let SimOp::Read(buf) = op {
+                for (byte, id) in buf.iter_mut().zip(bytes.iter().cycle()) {
+                    *byte = *id;
+                }
+                self.stats.read_checksum ^= SimStats::checksum(buf);
+            }
+        }
+        self.note(format_args!("outcome: ACK (device ID {id:?})"));
+        let _ = new.responder.send(Ok(new.transaction));
+    }
+
+    /// Report the next step in the current transaction
+    ///
+    /// Must only be called when [`Self::needs_transaction`] is false.
+    fn current(&mut self) -> Transaction<OnRead<'_>, OnWrite<'_>> {
         if self.need_to_report_deselect {
             self.need_to_report_deselect = false;
-            return Ok(Transaction::Deselect);
+            return Transaction::Deselect;
         }
 
-        let current = match &mut self.current_transaction {
-            Some(current) => current,
-            None => {
-                let new = self.from_controller.recv().await.ok_or(ErrorKind::Other)?;
-                println!("New transaction: {:?}", new.transaction);
-                self.current_transaction.insert(new)
+        let current = self
+            .current_transaction
+            .as_mut()
+            .expect("A transaction must have been received");
+
+        if let Some(address) = current.changed_address()
+            && !current.reported_restart
+        {
+            // A repeated start to a different address deselects us first
+            current.reported_restart = true;
+            if !self.matches(address) {
+                self.nak(NoAcknowledgeSource::Address);
+                self.need_to_report_deselect = false;
             }
-        };
+            return Transaction::Deselect;
+        }
 
-        let address = current.transaction.address;
+        let address = current
+            .transaction
+            .actions
+            .get(current.current_op)
+            .map_or(current.transaction.address, |&(address, _)| address);
 
-        Ok(match current.current_mut() {
+        let direction = match current.current() {
             None => {
                 // We are done with this one wait for the next
                 let done = self.current_transaction.take().unwrap();
                 assert_eq!(done.current_op, done.transaction.actions.len());
                 println!("ACK transaction: {:?}", done.transaction);
+                self.note(format_args!("outcome: ACK"));
+                for (_, op) in &done.transaction.actions {
+                    if let SimOp::Read(data) = op {
+                        self.note(format_args!("  controller read {data:02x?}"));
+                    }
+                }
                 let _ = done.responder.send(Ok(done.transaction));
-                Transaction::Deselect
+                return Transaction::Deselect;
             }
-            Some(SimOp::Read(_)) => Transaction::Read {
+            Some(SimOp::Read(_)) => Direction::Read,
+            Some(SimOp::Write(_)) => Direction::Write,
+        };
+
+        let refuse = !self.acks(address, direction);
+        if direction == Direction::Read && !refuse && self.echo.is_some() {
+            self.echo_read(address);
+            return self.current();
+        }
+        match direction {
+            Direction::Read => Transaction::Read {
                 address,
-                handler: OnRead::new(self),
+                handler: OnRead::new(self, address, refuse),
             },
-            Some(SimOp::Write(_)) => Transaction::Write {
+            Direction::Write => Transaction::Write {
                 address,
-                handler: OnWrite::new(self),
+                handler: OnWrite::new(self, address, refuse),
             },
-        })
+        }
+    }
+
+    /// Answer the current read operation with the last write, in echo mode.
+    fn echo_read(&mut self, address: AnyAddress) {
+        let fill = self.over_read.byte();
+        let echo = self.echo.as_deref().unwrap_or_default();
+        let Some(SimOp::Read(buf)) = self
+            .current_transaction
+            .as_mut()
+            .and_then(PartialTransaction::current_mut)
+        else {
+            unreachable!("Echo only answers reads");
+        };
+        let len = min(buf.len(), echo.len());
+        buf[..len].copy_from_slice(&echo[..len]);
+        buf[len..].fill(fill);
+        let size = buf.len();
+        let checksum = SimStats::checksum(buf);
+        let note = format!("target: echoed {:02x?}", &buf[..]);
+
+        self.stats.read_checksum ^= checksum;
+        self.note(format_args!("{note}"));
+        self.observe(|o| {
+            o.on_address_ack(address, Direction::Read);
+            o.on_data_ack(size);
+            o.on_complete(size);
+        });
+        self.next();
+    }
+
+    fn acks(&self, address: AnyAddress, direction: Direction) -> bool {
+        let ready = direction == Direction::Write
+            || self
+                .data_ready
+                .as_ref()
+                .is_none_or(|ready| ready.load(Ordering::Relaxed));
+        ready
+            && self
+                .ack_policy
+                .as_ref()
+                .is_none_or(|policy| policy(address, direction))
+    }
+}
+
+impl AsyncI2cTarget for SimTarget {
+    type Error = ErrorKind;
+    type Read<'a> = OnRead<'a>;
+    type Write<'a> = OnWrite<'a>;
+
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        self.matching_addresses()
+    }
+
+    fn ops_remaining(&self) -> Option<usize> {
+        self.current_ops_remaining()
+    }
+
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        self.clock_range
+    }
+
+    fn take_reset(&mut self) -> bool {
+        std::mem::take(&mut self.reset_pending)
+    }
+
+    async fn listen(
+        &mut self,
+    ) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        while self.needs_transaction() {
+            self.wait_for_step().await;
+            let new = self.from_controller.recv().await.ok_or(ErrorKind::Other)?;
+            self.receive(new);
+        }
+        Ok(self.current())
+    }
+}
+
+/// Blocking implementation of the target, for use outside of an async runtime.
+///
+/// Like [`Receiver::blocking_recv`], listening panics when called from within an async execution
+/// context.
+impl SyncI2cTarget for SimTarget {
+    type Error = ErrorKind;
+    type Read<'a> = OnRead<'a>;
+    type Write<'a> = OnWrite<'a>;
+
+    fn addresses(&self) -> impl Iterator<Item = AnyAddress> {
+        self.matching_addresses()
+    }
+
+    fn ops_remaining(&self) -> Option<usize> {
+        self.current_ops_remaining()
+    }
+
+    fn clock_range(&self) -> Option<(u32, u32)> {
+        self.clock_range
+    }
+
+    fn take_reset(&mut self) -> bool {
+        std::mem::take(&mut self.reset_pending)
+    }
+
+    fn listen(&mut self) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        while self.needs_transaction() {
+            self.blocking_wait_for_step();
+            let new = self
+                .from_controller
+                .blocking_recv()
+                .ok_or(ErrorKind::Other)?;
+            self.receive(new);
+        }
+        Ok(self.current())
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn listen_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<Transaction<Self::Read<'_>, Self::Write<'_>>>, Self::Error> {
+        const POLL_INTERVAL: Duration = Duration::from_micros(100);
+
+        let deadline = Instant::now() + timeout;
+        while self.needs_transaction() {
+            if !self.try_step() {
+                if Instant::now() >= deadline {
+                    return Ok(None);
+                }
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            match self.from_controller.try_recv() {
+                Ok(new) => self.receive(new),
+                Err(TryRecvError::Disconnected) => return Err(ErrorKind::Other),
+                Err(TryRecvError::Empty) if Instant::now() >= deadline => return Ok(None),
+                Err(TryRecvError::Empty) => std::thread::sleep(POLL_INTERVAL),
+            }
+        }
+        Ok(Some(self.current()))
+    }
+}
+
+/// Handle to single-step a [`SimTarget`], created by [`SimTarget::step_mode`]
+pub struct SimStepper {
+    steps: UnboundedSender<()>,
+}
+
+impl SimStepper {
+    /// Let the next transaction from the controller through to the target.
+    ///
+    /// Steps are queued, so calling this `n` times lets `n` transactions through.
+    pub fn step(&self) {
+        // The target is gone, so there is nothing left to step.
+        let _ = self.steps.send(());
     }
 }
 
 /// Read transaction handler for [`SimTarget`]
 pub struct OnRead<'a> {
     inner: &'a mut SimTarget,
+    address: AnyAddress,
     bytes_filled: usize,
     did_start: bool,
+    refuse: bool,
+    needs_accept: bool,
+    tail: u8,
 }
 
 impl<'a> OnRead<'a> {
-    const FILL: u8 = 0x2a;
-
-    const fn new(inner: &'a mut SimTarget) -> Self {
+    const fn new(inner: &'a mut SimTarget, address: AnyAddress, refuse: bool) -> Self {
+        let needs_accept = inner.strict_accept;
+        let tail = inner.over_read.byte();
         Self {
             inner,
+            address,
             bytes_filled: 0,
             did_start: false,
+            refuse,
+            needs_accept,
+            tail,
         }
     }
 
+    /// Acknowledge the address, before handling any data.
+    ///
+    /// Required with [`SimTarget::set_strict_accept`], optional otherwise. Returns
+    /// [`ErrorKind::NoAcknowledge`] if the ack policy refuses the address.
+    pub fn accept(mut self) -> Result<Self, ErrorKind> {
+        if self.refuse {
+            drop(self);
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
+        self.needs_accept = false;
+        self.start();
+        Ok(self)
+    }
+
     fn current_op_mut(&mut self) -> &mut SimOp {
         self.inner
             .current_transaction
@@ -140,49 +1105,164 @@ impl Drop for OnRead<'_> {
         if !self.did_start {
             self.inner.nak(NoAcknowledgeSource::Address);
         } else {
-            self.remaining().fill(Self::FILL);
+            let tail = self.tail;
+            let remaining = self.remaining();
+            remaining.fill(tail);
+            let len = remaining.len();
+            self.inner.stats.read_checksum ^= SimStats::checksum(remaining);
+            if len > 0 {
+                self.inner
+                    .note(format_args!("target: overrun, {len} bytes of {tail:#04x}"));
+            }
+            let size = self.bytes_filled + len;
+            self.inner.observe(|o| o.on_complete(size));
             self.inner.next()
         }
     }
 }
 
-impl AsyncReadTransaction for OnRead<'_> {
-    type Error = ErrorKind;
+impl OnRead<'_> {
+    /// Time to stretch the clock for a `handle_part` with a buffer of `len` bytes, or `Err` with
+    /// the time after which the controller aborts.
+    fn stretch(&mut self, len: usize) -> Result<Duration, Duration> {
+        let bytes = min(self.remaining().len(), len);
+        self.inner.stretch(self.inner.read_stretch, bytes)
+    }
 
-    async fn handle_part(mut self, buffer: &[u8]) -> Result<ReadResult<Self>, Self::Error> {
+    /// Give up on the transaction after the controller aborted it.
+    fn abort(self) -> ErrorKind {
+        self.inner.abort();
+        core::mem::forget(self);
+        ErrorKind::Bus
+    }
+
+    /// Acknowledge the address, once.
+    fn start(&mut self) {
+        if !self.did_start {
+            self.did_start = true;
+            let address = self.address;
+            self.inner
+                .observe(|o| o.on_address_ack(address, Direction::Read));
+        }
+    }
+
+    fn part(mut self, buffer: &[u8]) -> Result<ReadResult<Self>, ErrorKind> {
+        if self.refuse || self.needs_accept {
+            // Dropping before starting does not acknowledge the address
+            drop(self);
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
         if buffer.is_empty() {
             // do nothing
             return Ok(ReadResult::Partial(self));
         }
-        self.did_start = true;
+        self.start();
         let target = self.remaining();
 
         let len = min(target.len(), buffer.len());
         target[..len].copy_from_slice(&buffer[..len]);
         self.bytes_filled += len;
+        if len > 0 {
+            self.inner
+                .note(format_args!("target: provided {:02x?}", &buffer[..len]));
+            self.inner.stats.read_checksum ^= SimStats::checksum(&buffer[..len]);
+            self.inner.observe(|o| o.on_data_ack(len));
+        }
 
-        if self.remaining().is_empty() {
-            Ok(ReadResult::Complete(len))
+        Ok(if self.remaining().is_empty() {
+            // Dropping reports the completion
+            ReadResult::Complete(len)
         } else {
-            Ok(ReadResult::Partial(self))
+            ReadResult::Partial(self)
+        })
+    }
+
+    fn finish(self, tail: u8) -> Result<usize, ErrorKind> {
+        let mut this = match self.part(&[])? {
+            ReadResult::Complete(size) => return Ok(size),
+            ReadResult::Partial(this) => this,
+        };
+        this.start();
+        this.tail = tail;
+        // Dropping fills the rest of the read with the tail
+        Ok(this.remaining().len())
+    }
+}
+
+impl AsyncReadTransaction for OnRead<'_> {
+    type Error = ErrorKind;
+
+    async fn handle_part(mut self, buffer: &[u8]) -> Result<ReadResult<Self>, Self::Error> {
+        match self.stretch(buffer.len()) {
+            Ok(stretch) if !stretch.is_zero() => tokio::time::sleep(stretch).await,
+            Ok(_) => {}
+            Err(limit) => {
+                tokio::time::sleep(limit).await;
+                return Err(self.abort());
+            }
         }
+        self.part(buffer)
+    }
+
+    async fn finish_with(self, tail: u8) -> Result<usize, Self::Error> {
+        self.finish(tail)
+    }
+}
+
+impl SyncReadTransaction for OnRead<'_> {
+    type Error = ErrorKind;
+
+    fn handle_part(mut self, buffer: &[u8]) -> Result<ReadResult<Self>, Self::Error> {
+        match self.stretch(buffer.len()) {
+            Ok(stretch) => std::thread::sleep(stretch),
+            Err(limit) => {
+                std::thread::sleep(limit);
+                return Err(self.abort());
+            }
+        }
+        self.part(buffer)
+    }
+
+    fn finish_with(self, tail: u8) -> Result<usize, Self::Error> {
+        self.finish(tail)
     }
 }
 
 /// Write transaction handler for [`SimTarget`]
 pub struct OnWrite<'a> {
     inner: &'a mut SimTarget,
+    address: AnyAddress,
     bytes_read: usize,
     did_start: bool,
+    refuse: bool,
+    needs_accept: bool,
 }
 
 impl<'a> OnWrite<'a> {
-    const fn new(inner: &'a mut SimTarget) -> Self {
+    const fn new(inner: &'a mut SimTarget, address: AnyAddress, refuse: bool) -> Self {
+        let needs_accept = inner.strict_accept;
         Self {
             inner,
+            address,
             bytes_read: 0,
             did_start: false,
+            refuse,
+            needs_accept,
+        }
+    }
+
+    /// Acknowledge the address, before handling any data.
+    ///
+    /// Required with [`SimTarget::set_strict_accept`], optional otherwise. Returns
+    /// [`ErrorKind::NoAcknowledge`] if the ack policy refuses the address.
+    pub fn accept(mut self) -> Result<Self, ErrorKind> {
+        if self.refuse {
+            drop(self);
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
         }
+        self.needs_accept = false;
+        self.start();
+        Ok(self)
     }
 
     fn current_op(&self) -> &SimOp {
@@ -207,43 +1287,158 @@ impl<'a> OnWrite<'a> {
     fn disarm(self) {
         core::mem::forget(self);
     }
-}
 
-impl Drop for OnWrite<'_> {
-    fn drop(&mut self) {
+    /// Not acknowledge the pending byte, or the address if nothing was handled yet.
+    fn nak(&mut self) {
         if !self.did_start {
             self.inner.nak(NoAcknowledgeSource::Address);
         } else {
             self.inner.nak(NoAcknowledgeSource::Data);
         }
     }
+
+    fn end(mut self) -> Result<usize, ErrorKind> {
+        self.nak();
+        self.disarm();
+        Ok(0)
+    }
 }
 
-impl AsyncWriteTransaction for OnWrite<'_> {
-    type Error = ErrorKind;
+impl Drop for OnWrite<'_> {
+    fn drop(&mut self) {
+        self.nak();
+    }
+}
 
-    async fn handle_part(mut self, buffer: &mut [u8]) -> Result<WriteResult<Self>, Self::Error> {
+impl OnWrite<'_> {
+    /// Time to stretch the clock for a `handle_part` with a buffer of `len` bytes, or `Err` with
+    /// the time after which the controller aborts.
+    fn stretch(&mut self, len: usize) -> Result<Duration, Duration> {
+        let bytes = min(self.remaining().len(), len);
+        self.inner.stretch(self.inner.write_stretch, bytes)
+    }
+
+    /// Give up on the transaction after the controller aborted it.
+    fn abort(self) -> ErrorKind {
+        self.inner.abort();
+        self.disarm();
+        ErrorKind::Bus
+    }
+
+    /// Acknowledge the address, once.
+    fn start(&mut self) {
+        if !self.did_start {
+            self.did_start = true;
+            let address = self.address;
+            self.inner
+                .observe(|o| o.on_address_ack(address, Direction::Write));
+            if let Some(echo) = &mut self.inner.echo {
+                echo.clear();
+            }
+        }
+    }
+
+    fn part(mut self, buffer: &mut [u8]) -> Result<WriteResult<Self>, ErrorKind> {
+        if self.refuse || self.needs_accept {
+            // Dropping before starting does not acknowledge the address
+            drop(self);
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
         if buffer.is_empty() {
             // do nothing
             return Ok(WriteResult::Partial(self));
         }
-        self.did_start = true;
+        self.start();
         let source = self.remaining();
 
         let len = min(source.len(), buffer.len());
         buffer[..len].copy_from_slice(&source[..len]);
         self.bytes_read += len;
+        if let Some(echo) = &mut self.inner.echo {
+            echo.extend_from_slice(&buffer[..len]);
+        }
+        if len > 0 {
+            self.inner
+                .note(format_args!("target: received {:02x?}", &buffer[..len]));
+            self.inner.stats.write_checksum ^= SimStats::checksum(&buffer[..len]);
+            self.inner.observe(|o| o.on_data_ack(len));
+        }
 
-        if self.remaining().is_empty() {
+        Ok(if self.remaining().is_empty() {
             if buffer.len() == len {
-                Ok(WriteResult::Partial(self))
+                WriteResult::Partial(self)
             } else {
+                let size = self.bytes_read;
+                self.inner.observe(|o| o.on_complete(size));
                 self.inner.next();
                 self.disarm();
-                Ok(WriteResult::Complete(len))
+                WriteResult::Complete(len)
             }
         } else {
-            Ok(WriteResult::Partial(self))
+            WriteResult::Partial(self)
+        })
+    }
+
+    fn reject(mut self) -> Result<(), ErrorKind> {
+        if self.refuse || self.needs_accept {
+            // Dropping before starting does not acknowledge the address
+            drop(self);
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
         }
+        self.start();
+        let Some(&byte) = self.remaining().first() else {
+            // No data to reject, the write completes as usual
+            return self.part(&mut [0]).map(drop);
+        };
+        self.inner
+            .note(format_args!("target: rejected {byte:#04x}"));
+        self.end().map(drop)
+    }
+}
+
+impl AsyncWriteTransaction for OnWrite<'_> {
+    type Error = ErrorKind;
+
+    async fn handle_part(mut self, buffer: &mut [u8]) -> Result<WriteResult<Self>, Self::Error> {
+        match self.stretch(buffer.len()) {
+            Ok(stretch) if !stretch.is_zero() => tokio::time::sleep(stretch).await,
+            Ok(_) => {}
+            Err(limit) => {
+                tokio::time::sleep(limit).await;
+                return Err(self.abort());
+            }
+        }
+        self.part(buffer)
+    }
+
+    async fn finish(self) -> Result<usize, Self::Error> {
+        self.end()
+    }
+
+    async fn reject_first_byte(self) -> Result<(), Self::Error> {
+        self.reject()
+    }
+}
+
+impl SyncWriteTransaction for OnWrite<'_> {
+    type Error = ErrorKind;
+
+    fn handle_part(mut self, buffer: &mut [u8]) -> Result<WriteResult<Self>, Self::Error> {
+        match self.stretch(buffer.len()) {
+            Ok(stretch) => std::thread::sleep(stretch),
+            Err(limit) => {
+                std::thread::sleep(limit);
+                return Err(self.abort());
+            }
+        }
+        self.part(buffer)
+    }
+
+    fn finish(self) -> Result<usize, Self::Error> {
+        self.end()
+    }
+
+    fn reject_first_byte(self) -> Result<(), Self::Error> {
+        self.reject()
     }
 }
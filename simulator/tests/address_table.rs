@@ -0,0 +1,49 @@
+use embedded_hal_i2c::AnyAddress;
+use embedded_hal_i2c::address_table::{AddressTable, AddressTableError};
+
+#[test]
+fn both_address_widths() {
+    let mut table = AddressTable::<&str, 4>::new();
+    assert_eq!(table.insert(AnyAddress::Seven(0x50), "eeprom"), Ok(None));
+    assert_eq!(table.insert(AnyAddress::Ten(0x50), "sensor"), Ok(None));
+
+    assert_eq!(table.get(AnyAddress::Seven(0x50)), Some(&"eeprom"));
+    assert_eq!(table.get(AnyAddress::Ten(0x50)), Some(&"sensor"));
+    assert_eq!(table.get(AnyAddress::Seven(0x51)), None);
+
+    assert_eq!(
+        table.insert(AnyAddress::Ten(0x50), "display"),
+        Ok(Some("sensor"))
+    );
+    assert_eq!(table.len(), 2);
+    assert_eq!(table.remove(AnyAddress::Seven(0x50)), Some("eeprom"));
+    assert_eq!(table.get(AnyAddress::Ten(0x50)), Some(&"display"));
+}
+
+#[test]
+fn rejected_addresses() {
+    let mut table = AddressTable::<(), 1>::new();
+    assert_eq!(
+        table.insert(AnyAddress::Seven(0x00), ()),
+        Err(AddressTableError::Reserved)
+    );
+    assert_eq!(
+        table.insert(AnyAddress::Seven(0x78), ()),
+        Err(AddressTableError::Reserved)
+    );
+    assert_eq!(
+        table.insert(AnyAddress::Seven(0x80), ()),
+        Err(AddressTableError::OutOfRange)
+    );
+    assert_eq!(
+        table.insert(AnyAddress::Ten(0x400), ()),
+        Err(AddressTableError::OutOfRange)
+    );
+    assert!(table.is_empty());
+
+    assert_eq!(table.insert(AnyAddress::Ten(0x000), ()), Ok(None));
+    assert_eq!(
+        table.insert(AnyAddress::Seven(0x10), ()),
+        Err(AddressTableError::Full)
+    );
+}
@@ -0,0 +1,8 @@
+// The expected diagnostics are rustc's full output, which changes between releases. Regenerate
+// them with `TRYBUILD=overwrite` when moving this to a new toolchain.
+#[rustversion::attr(not(stable(1.95)), ignore = "diagnostics are pinned to rustc 1.95")]
+#[test]
+fn compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/compile_fail/*.rs");
+}
@@ -0,0 +1,10 @@
+use simulator::channel::{ByteLen, ChannelTarget, Registers};
+use simulator::simulator;
+
+fn main() {
+    let (_c, t) = simulator();
+    let (events, _received) = tokio::sync::mpsc::channel(1);
+    let address = embedded_hal_i2c::AnyAddress::Seven(0x42);
+    let mut target = ChannelTarget::new(t, address, events, Registers::default());
+    target.set_on_read(ByteLen(4), Vec::new);
+}
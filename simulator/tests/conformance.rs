@@ -1,7 +1,7 @@
 use embedded_hal_i2c::{
     AnyAddress, AsyncI2cController, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction,
     Error, ErrorKind, NoAcknowledgeSource, Operation, ReadResult, Transaction,
-    TransactionExpectRead, TransactionExpectWrite, WriteResult,
+    TransactionExpectEither, TransactionExpectRead, TransactionExpectWrite, WriteResult,
 };
 use simulator::simulator;
 
@@ -381,6 +381,109 @@ async fn test_address_nack() {
     tokio::join!(control, target);
 }
 
+#[tokio::test]
+async fn test_address_nack_strict_deselect() {
+    let (mut c, mut t) = simulator();
+    t.set_strict_deselect(true);
+
+    let control = async move {
+        assert!(matches!(
+            c.transaction(0x20u8, &mut [Operation::Write(&[1, 2, 3, 4])])
+                .await
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+        ));
+        assert!(matches!(
+            c.transaction(0x20u8, &mut [Operation::Read(&mut [0, 0, 0, 0])])
+                .await
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+        ));
+        assert!(matches!(
+            c.transaction(
+                0x20u8,
+                &mut [
+                    Operation::Write(&[1, 2, 3, 4]),
+                    Operation::Write(&[1, 2, 3, 4])
+                ]
+            )
+            .await
+            .unwrap_err()
+            .kind(),
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+        ));
+    };
+
+    let target = async move {
+        let Transaction::Write {
+            address: AnyAddress::Seven(0x20),
+            handler,
+        } = t.listen().await.unwrap()
+        else {
+            panic!("Unexpected transaction type");
+        };
+        drop(handler);
+        t.expect_no_spurious_deselect();
+        let Transaction::Read {
+            address: AnyAddress::Seven(0x20),
+            handler,
+        } = t.listen().await.unwrap()
+        else {
+            panic!("Unexpected transaction type");
+        };
+        drop(handler);
+        t.expect_no_spurious_deselect();
+
+        let Transaction::Write {
+            address: AnyAddress::Seven(0x20),
+            handler,
+        } = t.listen().await.unwrap()
+        else {
+            panic!("Unexpected transaction type");
+        };
+        let mut data = [0u8; 4];
+        assert_eq!(handler.handle_complete(&mut data).await.unwrap(), 4);
+        let Transaction::Write {
+            address: AnyAddress::Seven(0x20),
+            handler,
+        } = t.listen().await.unwrap()
+        else {
+            panic!("Unexpected transaction type");
+        };
+        drop(handler);
+        // Still required, the first write was acknowledged
+        let Transaction::Deselect = t.listen().await.unwrap() else {
+            panic!("Unexpected transaction type");
+        };
+    };
+
+    tokio::join!(control, target);
+}
+
+#[test]
+#[should_panic(expected = "listen would report a deselect next")]
+fn spurious_deselect_detected() {
+    let (mut c, mut t) = simulator();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let control = async {
+            let _ = c.write(0x20u8, &[1]).await;
+        };
+        let target = async {
+            let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+                panic!("Unexpected transaction type");
+            };
+            drop(handler);
+        };
+        tokio::join!(control, target);
+    });
+    t.expect_no_spurious_deselect();
+}
+
 #[tokio::test]
 async fn handle_part_edgecases() {
     let (mut c, mut t) = simulator();
@@ -747,6 +850,80 @@ async fn listen_expect_mismatch() {
     tokio::join!(control, target);
 }
 
+#[tokio::test]
+async fn listen_expect_either() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        assert!(
+            c.transaction(0x20u8, &mut [Operation::Write(&[1, 2, 3])])
+                .await
+                .is_ok()
+        );
+
+        let mut data = [0u8; 4];
+        assert!(
+            c.transaction(0x20u8, &mut [Operation::Read(&mut data)])
+                .await
+                .is_ok()
+        );
+        assert_eq!(data, [5, 6, 7, 8]);
+
+        assert!(
+            c.transaction(0x21u8, &mut [Operation::Write(&[9])])
+                .await
+                .is_ok()
+        );
+    };
+
+    let target = async move {
+        let mut data = [0u8; 4];
+        let TransactionExpectEither::ExpectedCompleteWrite { size: 3 } = t
+            .listen_expect_either(0x20u8.into(), &[5, 6, 7, 8], &mut data)
+            .await
+            .unwrap()
+        else {
+            panic!("Unexpected transaction type");
+        };
+        assert_eq!(data, [1, 2, 3, 0]);
+        let Transaction::Deselect = t.listen().await.unwrap() else {
+            panic!("Unexpected transaction type");
+        };
+
+        let mut data = [0u8; 4];
+        let TransactionExpectEither::ExpectedCompleteRead { size: 4 } = t
+            .listen_expect_either(0x20u8.into(), &[5, 6, 7, 8], &mut data)
+            .await
+            .unwrap()
+        else {
+            panic!("Unexpected transaction type");
+        };
+        assert_eq!(data, [0; 4]);
+        let Transaction::Deselect = t.listen().await.unwrap() else {
+            panic!("Unexpected transaction type");
+        };
+
+        let TransactionExpectEither::Write {
+            address: AnyAddress::Seven(0x21),
+            handler,
+        } = t
+            .listen_expect_either(0x20u8.into(), &[5, 6, 7, 8], &mut data)
+            .await
+            .unwrap()
+        else {
+            panic!("Unexpected transaction type");
+        };
+        assert_eq!(data, [0; 4]);
+        assert_eq!(handler.handle_complete(&mut data).await.unwrap(), 1);
+        assert_eq!(data, [9, 0, 0, 0]);
+        let Transaction::Deselect = t.listen().await.unwrap() else {
+            panic!("Unexpected transaction type");
+        };
+    };
+
+    tokio::join!(control, target);
+}
+
 #[tokio::test]
 async fn listen_expect_edgecases() {
     let (mut c, mut t) = simulator();
@@ -0,0 +1,209 @@
+use embedded_hal_i2c::{
+    AnyAddress, ReadResult, Transaction, TransactionExpectEither, TransactionExpectRead,
+    TransactionExpectWrite, WriteResult,
+};
+
+type Either = TransactionExpectEither<u8, u16>;
+
+const ADDR: AnyAddress = AnyAddress::Seven(0x42);
+
+#[test]
+fn expect_read_round_trip() {
+    let either = Either::from(TransactionExpectRead::ExpectedPartialRead { handler: 1 });
+    let Ok(TransactionExpectRead::ExpectedPartialRead { handler: 1 }) = either.try_into() else {
+        panic!()
+    };
+
+    let either = Either::from(TransactionExpectRead::ExpectedCompleteRead { size: 3 });
+    let Err(TransactionExpectEither::ExpectedCompleteRead { size: 3 }) =
+        TransactionExpectWrite::try_from(either)
+    else {
+        panic!()
+    };
+}
+
+#[test]
+fn expect_write_round_trip() {
+    let either = Either::from(TransactionExpectWrite::ExpectedPartialWrite { handler: 2 });
+    let Ok(TransactionExpectWrite::ExpectedPartialWrite { handler: 2 }) = either.try_into() else {
+        panic!()
+    };
+
+    let either = Either::from(TransactionExpectWrite::ExpectedCompleteWrite { size: 3 });
+    let Err(TransactionExpectEither::ExpectedCompleteWrite { size: 3 }) =
+        TransactionExpectRead::try_from(either)
+    else {
+        panic!()
+    };
+}
+
+#[test]
+fn unexpected_transactions_convert_both_ways() {
+    // Which expectation a plain transaction came from is lost
+    let either = Either::from(TransactionExpectRead::Write {
+        address: ADDR,
+        handler: 2,
+    });
+    let Ok(TransactionExpectWrite::Write {
+        address: ADDR,
+        handler: 2,
+    }) = either.try_into()
+    else {
+        panic!()
+    };
+
+    let either = Either::from(Transaction::Read {
+        address: ADDR,
+        handler: 1,
+    });
+    let Ok(Transaction::Read {
+        address: ADDR,
+        handler: 1,
+    }) = either.try_into()
+    else {
+        panic!()
+    };
+
+    let Ok(Transaction::<u8, u16>::Deselect) = TransactionExpectRead::Deselect.try_into() else {
+        panic!()
+    };
+    let Err(TransactionExpectWrite::<u8, u16>::ExpectedCompleteWrite { size: 0 }) =
+        Transaction::try_from(TransactionExpectWrite::ExpectedCompleteWrite { size: 0 })
+    else {
+        panic!()
+    };
+}
+
+#[test]
+fn ten_bit_bytes_round_trip() {
+    for address in [0x000, 0x0FF, 0x100, 0x2A5, 0x3FF] {
+        let bytes = AnyAddress::Ten(address).to_ten_bit_bytes().unwrap();
+        assert_eq!(
+            AnyAddress::from_ten_bit_bytes(bytes[0], bytes[1]),
+            Some(AnyAddress::Ten(address))
+        );
+    }
+    assert_eq!(
+        AnyAddress::Ten(0x2A5).to_ten_bit_bytes(),
+        Some([0b1111_0100, 0xA5])
+    );
+    // The R/W bit does not matter
+    assert_eq!(
+        AnyAddress::from_ten_bit_bytes(0b1111_0101, 0xA5),
+        Some(AnyAddress::Ten(0x2A5))
+    );
+}
+
+#[test]
+fn ten_bit_bytes_rejected() {
+    assert_eq!(AnyAddress::from_ten_bit_bytes(0b1110_0000, 0), None);
+    assert_eq!(AnyAddress::from_ten_bit_bytes(0b1111_1000, 0), None);
+    assert_eq!(AnyAddress::from_ten_bit_bytes(0x42 << 1, 0), None);
+    assert_eq!(ADDR.to_ten_bit_bytes(), None);
+    assert_eq!(AnyAddress::Ten(0x400).to_ten_bit_bytes(), None);
+}
+
+#[test]
+fn raw_address() {
+    assert_eq!(ADDR.raw(), 0x42);
+    assert!(!ADDR.is_ten_bit());
+    assert_eq!(AnyAddress::Ten(0x2A5).raw(), 0x2A5);
+    assert!(AnyAddress::Ten(0x2A5).is_ten_bit());
+}
+
+#[test]
+fn concrete_address_conversions() {
+    assert_eq!(u8::try_from(ADDR), Ok(0x42));
+    assert_eq!(u8::try_from(AnyAddress::Ten(0x42)), Ok(0x42));
+    assert_eq!(
+        u8::try_from(AnyAddress::Ten(0x2A5)),
+        Err(AnyAddress::Ten(0x2A5))
+    );
+
+    assert_eq!(u16::try_from(ADDR), Ok(0x42));
+    assert_eq!(u16::try_from(AnyAddress::Ten(0x2A5)), Ok(0x2A5));
+    assert_eq!(
+        u16::try_from(AnyAddress::Ten(0x400)),
+        Err(AnyAddress::Ten(0x400))
+    );
+
+    assert_eq!(AnyAddress::from(u8::try_from(ADDR).unwrap()), ADDR);
+}
+
+#[test]
+fn seven_bit_address_out_of_range() {
+    let address = AnyAddress::Seven(0x80);
+    assert_eq!(u8::try_from(address), Err(address));
+    assert_eq!(u16::try_from(address), Err(address));
+    assert_eq!(u8::try_from(AnyAddress::Seven(0x7F)), Ok(0x7F));
+}
+
+struct Wrapped<H>(H);
+
+#[test]
+fn map_handlers() {
+    let read = Transaction::<u8, u16>::Read {
+        address: ADDR,
+        handler: 1,
+    };
+    let Transaction::Read {
+        address: ADDR,
+        handler: Wrapped(1),
+    } = read.map_handlers(Wrapped, Wrapped)
+    else {
+        panic!()
+    };
+
+    let write = TransactionExpectRead::<u8, u16>::Write {
+        address: ADDR,
+        handler: 2,
+    };
+    let TransactionExpectRead::Write {
+        handler: Wrapped(2),
+        ..
+    } = write.map_handlers(Wrapped, Wrapped)
+    else {
+        panic!()
+    };
+
+    let partial = TransactionExpectWrite::<u8, u16>::ExpectedPartialWrite { handler: 3 };
+    let TransactionExpectWrite::ExpectedPartialWrite {
+        handler: Wrapped(3),
+    } = partial.map_handlers(Wrapped, Wrapped)
+    else {
+        panic!()
+    };
+
+    let complete = Either::ExpectedCompleteRead { size: 4 };
+    let TransactionExpectEither::ExpectedCompleteRead { size: 4 } =
+        complete.map_handlers(Wrapped, |h| Wrapped(u32::from(h)))
+    else {
+        panic!()
+    };
+}
+
+#[test]
+fn read_result_accessors() {
+    let partial = ReadResult::Partial(1u8);
+    assert_eq!(partial.completed(), None);
+    assert!(partial.is_partial());
+    assert!(!partial.is_complete());
+
+    let complete = ReadResult::<u8>::Complete(3);
+    assert_eq!(complete.completed(), Some(3));
+    assert!(!complete.is_partial());
+    assert!(complete.is_complete());
+}
+
+#[test]
+fn write_result_accessors() {
+    let partial = WriteResult::Partial(1u8);
+    assert_eq!(partial.completed(), None);
+    assert!(partial.is_partial());
+    assert!(!partial.is_complete());
+
+    let complete = WriteResult::<u8>::Complete(0);
+    assert_eq!(complete.completed(), Some(0));
+    assert!(!complete.is_partial());
+    assert!(complete.is_complete());
+}
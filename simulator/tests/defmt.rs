@@ -0,0 +1,65 @@
+use embedded_hal_i2c::defmt_target::DefmtTarget;
+use embedded_hal_i2c::{
+    AnyAddress, AsyncI2cController, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction,
+    ErrorKind, NoAcknowledgeSource, Transaction,
+};
+use simulator::simulator;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of bytes logged, as the encoded frames cannot be decoded here
+static LOGGED: AtomicUsize = AtomicUsize::new(0);
+
+#[defmt::global_logger]
+struct Capture;
+
+unsafe impl defmt::Logger for Capture {
+    fn acquire() {}
+    unsafe fn flush() {}
+    unsafe fn release() {}
+    unsafe fn write(bytes: &[u8]) {
+        LOGGED.fetch_add(bytes.len(), Ordering::Relaxed);
+    }
+}
+
+defmt::timestamp!("");
+
+const A7: u8 = 0x42;
+
+#[tokio::test]
+async fn forwards_to_inner() {
+    let (mut c, mut t) = simulator();
+    t.set_ack_policy(|_, direction| direction == simulator::target::Direction::Read);
+    let mut t = DefmtTarget::new(t);
+
+    let control = async move {
+        let mut response = [0; 2];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [1, 2]);
+
+        let result = c.write(A7, &[3]).await.unwrap_err();
+        assert_eq!(
+            result,
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+        );
+    };
+
+    let target = async move {
+        let Transaction::Read { address, handler } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(address, AnyAddress::Seven(A7));
+        handler.handle_complete(&[1, 2], 0xFF).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        // Errors are logged regardless of the configured log level
+        let logged = LOGGED.load(Ordering::Relaxed);
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0]).await.unwrap_err();
+        assert!(LOGGED.load(Ordering::Relaxed) > logged);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
@@ -0,0 +1,30 @@
+use embedded_hal_i2c::{ErrorKind, NoAcknowledgeSource, is_bus_fault, is_retryable};
+
+#[test]
+fn classification() {
+    let cases = [
+        (ErrorKind::Bus, false, true),
+        (ErrorKind::ArbitrationLoss, true, false),
+        (
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            true,
+            false,
+        ),
+        (
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+            false,
+            false,
+        ),
+        (
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            false,
+            false,
+        ),
+        (ErrorKind::Overrun, false, false),
+        (ErrorKind::Other, false, false),
+    ];
+    for (kind, retryable, bus_fault) in cases {
+        assert_eq!(is_retryable(kind), retryable, "{kind:?}");
+        assert_eq!(is_bus_fault(kind), bus_fault, "{kind:?}");
+    }
+}
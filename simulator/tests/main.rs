@@ -1,8 +1,30 @@
+use embedded_hal_i2c::continuation::ReadContinuation;
+use embedded_hal_i2c::controller_ext::{
+    AsyncI2cControllerExt, AsyncI2cMultiAddressController, DEVICE_ID_ADDRESS, DeviceId,
+    PageWriteError,
+};
+use embedded_hal_i2c::length_limited::LengthLimitedTarget;
+use embedded_hal_i2c::progress::ProgressController;
+use embedded_hal_i2c::register::{RegisterAccess, RegisterAccessListener, RegisterAddressWidth};
+use embedded_hal_i2c::retry::{RetryError, RetryHandler};
+use embedded_hal_i2c::seven_bit::SevenBitTarget;
+use embedded_hal_i2c::transaction_log::LoggingTarget;
 use embedded_hal_i2c::{
     AnyAddress, AsyncI2cController, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction,
-    ErrorKind, NoAcknowledgeSource, Operation, ReadResult, Transaction, WriteResult,
+    ErrorKind, NoAcknowledgeSource, Operation, ReadResult, Transaction, TransactionExpectWrite,
+    TransactionTag, WriteResult,
+};
+use simulator::channel::{
+    ByteLen, ChannelTarget, Fifo, ReadLengthMismatch, RegAddr, RegisterWrite, Registers,
 };
+use simulator::multiplex::{AddressEvent, MultiplexTarget};
 use simulator::simulator;
+use simulator::target::{Direction, OverRead, SimObserver, SimStats};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
 
 const A7: u8 = 0x42;
 const ADDR: AnyAddress = AnyAddress::Seven(A7);
@@ -192,3 +214,2521 @@ async fn write_nak() {
 
     tokio::join!(control, target);
 }
+
+#[tokio::test]
+async fn cloned_controllers() {
+    let (mut c1, mut t) = simulator();
+    let mut c2 = c1.clone();
+
+    let control1 = async move {
+        for i in 0..3 {
+            let mut response = [0];
+            c1.write_read(A7, &[i], &mut response).await.unwrap();
+            assert_eq!(response, [i + 1]);
+        }
+    };
+
+    let control2 = async move {
+        for i in 10..13 {
+            let mut response = [0];
+            c2.write_read(A7, &[i], &mut response).await.unwrap();
+            assert_eq!(response, [i + 1]);
+        }
+    };
+
+    let target = async move {
+        for _ in 0..6 {
+            let Transaction::Write { address, handler } = t.listen().await.unwrap() else {
+                panic!()
+            };
+            assert_eq!(address, ADDR);
+            let mut buf = [0];
+            handler.handle_complete(&mut buf).await.unwrap();
+
+            let Transaction::Read { address, handler } = t.listen().await.unwrap() else {
+                panic!()
+            };
+            assert_eq!(address, ADDR);
+            handler.handle_complete(&[buf[0] + 1], 0xFF).await.unwrap();
+
+            assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        }
+    };
+
+    tokio::join!(control1, control2, target);
+}
+
+#[tokio::test]
+async fn power_cycle_aborts_transaction() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        let mut response = [0; 2];
+        let result = c.write_read(A7, &[1], &mut response).await.unwrap_err();
+        assert_eq!(
+            result,
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown)
+        );
+
+        c.write(A7, &[2]).await.unwrap();
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0]).await.unwrap();
+
+        t.power_cycle();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        assert!(t.take_reset());
+        assert!(!t.take_reset());
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buf = [0];
+        handler.handle_complete(&mut buf).await.unwrap();
+        assert_eq!(buf, [2]);
+
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn power_cycle_idle_strict_deselect() {
+    let (mut c, mut t) = simulator();
+    t.set_strict_deselect(true);
+
+    t.power_cycle();
+    t.expect_no_spurious_deselect();
+    assert!(t.take_reset());
+
+    let control = async move {
+        c.write(A7, &[1]).await.unwrap();
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0]).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn length_limited_write() {
+    let (mut c, mut t) = simulator();
+    t.record_transcript();
+    let mut t = LengthLimitedTarget::new(t, 4);
+
+    let control = async move {
+        c.write(A7, &[1, 2, 3, 4]).await.unwrap();
+
+        let result = c.write(A7, &[1, 2, 3, 4, 5, 6]).await.unwrap_err();
+        assert_eq!(result, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data));
+
+        let mut response = [0; 6];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [1, 2, 3, 4, 0x2a, 0x2a]);
+    };
+
+    let target = async {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buf = [0; 8];
+        assert_eq!(handler.handle_complete(&mut buf).await.unwrap(), 4);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buf = [0; 8];
+        let WriteResult::Partial(handler) = handler.handle_part(&mut buf[..3]).await.unwrap()
+        else {
+            panic!()
+        };
+        let WriteResult::Complete(1) = handler.handle_part(&mut buf[3..]).await.unwrap() else {
+            panic!()
+        };
+        // Nothing past the limit ends up in the buffer
+        assert_eq!(buf, [1, 2, 3, 4, 0, 0, 0, 0]);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(
+            handler
+                .handle_complete(&[1, 2, 3, 4, 5], 0xff)
+                .await
+                .unwrap(),
+            4
+        );
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+
+    // The byte right after the limit is the one not acknowledged
+    assert!(t.into_inner().transcript().contains(
+        "\
+target: received [01, 02, 03]
+target: received [04]
+target: received [05]
+outcome: NAK Data
+"
+    ));
+}
+
+#[tokio::test]
+async fn masked_address() {
+    let (mut c, mut t) = simulator();
+    t.set_address_mask(AnyAddress::Seven(0x40), 0x7c);
+
+    let control = async move {
+        c.write(0x42_u8, &[1]).await.unwrap();
+
+        let result = c.write(0x50_u8, &[2]).await.unwrap_err();
+        assert_eq!(
+            result,
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+        );
+
+        c.write(0x41_u8, &[3]).await.unwrap();
+    };
+
+    let target = async move {
+        let Transaction::Write { address, handler } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(address, AnyAddress::Seven(0x42));
+        let mut buf = [0];
+        handler.handle_complete(&mut buf).await.unwrap();
+        assert_eq!(buf, [1]);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        // The write to 0x50 is never seen, and the expected address is compared exactly.
+        let mut buf = [0];
+        let TransactionExpectWrite::Write { address, handler } = t
+            .listen_expect_write(AnyAddress::Seven(0x40), &mut buf)
+            .await
+            .unwrap()
+        else {
+            panic!()
+        };
+        assert_eq!(address, AnyAddress::Seven(0x41));
+        handler.handle_complete(&mut buf).await.unwrap();
+        assert_eq!(buf, [3]);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn read_chunked() {
+    let (mut c, mut t) = simulator();
+    let data: [u8; 100] = std::array::from_fn(|n| n as u8);
+
+    let control = async move {
+        let mut response = [0; 100];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, data);
+
+        let mut response = [0; 50];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, data[..50]);
+
+        let mut response = [0; 104];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response[..100], data);
+        assert_eq!(response[100..], [0x2a; 4]);
+    };
+
+    let target = async move {
+        for expected in [100, 50, 100] {
+            let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+                panic!()
+            };
+            assert_eq!(handler.handle_chunked(&data, 8).await.unwrap(), expected);
+            assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        }
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn nak_stats() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        c.write(A7, &[1]).await.unwrap_err();
+        c.write(A7, &[1, 2]).await.unwrap_err();
+        c.read(A7, &mut [0]).await.unwrap_err();
+        c.write(A7, &[1]).await.unwrap();
+    };
+
+    let target = async move {
+        assert_eq!(t.stats(), SimStats::default());
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        drop(handler);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0]).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        drop(handler);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0]).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let stats = t.stats();
+        assert_eq!(stats.address_naks, 2);
+        assert_eq!(stats.data_naks, 1);
+        assert_eq!(stats.unknown_naks, 0);
+
+        t.reset_stats();
+        assert_eq!(t.stats(), SimStats::default());
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn direction_follows_operation() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        let mut a = [0];
+        let mut b = [0];
+        let mut operations = [
+            Operation::Write(&[1]),
+            Operation::Read(&mut a),
+            Operation::Write(&[2]),
+            Operation::Read(&mut b),
+        ];
+        c.transaction(A7, &mut operations).await.unwrap();
+        assert_eq!(a, [3]);
+        assert_eq!(b, [4]);
+    };
+
+    let target = async move {
+        for (write, read) in [(1, 3), (2, 4)] {
+            let Transaction::Write { address, handler } = t.listen().await.unwrap() else {
+                panic!("Expected write direction")
+            };
+            assert_eq!(address, ADDR);
+            let mut buf = [0];
+            handler.handle_complete(&mut buf).await.unwrap();
+            assert_eq!(buf, [write]);
+
+            let Transaction::Read { address, handler } = t.listen().await.unwrap() else {
+                panic!("Expected read direction")
+            };
+            assert_eq!(address, ADDR);
+            handler.handle_complete(&[read], 0xFF).await.unwrap();
+        }
+
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn large_transfers() {
+    const SIZE: usize = 64 * 1024;
+    let (c, mut t) = simulator();
+    let mut c = c.with_capacity(SIZE);
+    let data: Vec<u8> = (0..SIZE).map(|n| (n % 251) as u8).collect();
+    let expected = data.clone();
+
+    let control = async move {
+        for _ in 0..4 {
+            c.write(A7, &data).await.unwrap();
+
+            let mut response = vec![0; SIZE];
+            c.read(A7, &mut response).await.unwrap();
+            assert_eq!(response, data);
+        }
+    };
+
+    let target = async move {
+        let mut buf = vec![0; SIZE];
+        for _ in 0..4 {
+            let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+                panic!()
+            };
+            assert_eq!(handler.handle_complete(&mut buf).await.unwrap(), SIZE);
+            assert_eq!(buf, expected);
+            assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+            let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+                panic!()
+            };
+            assert_eq!(handler.handle_complete(&buf, 0xFF).await.unwrap(), SIZE);
+            assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        }
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn write_counted() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        c.write(A7, &[1, 2, 3, 4, 5]).await.unwrap();
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+
+        let (0, WriteResult::Partial(handler)) =
+            handler.handle_part_counted(&mut []).await.unwrap()
+        else {
+            panic!("Empty buffer acknowledges nothing")
+        };
+
+        let mut buf = [0; 3];
+        let (2, WriteResult::Partial(handler)) =
+            handler.handle_part_counted(&mut buf).await.unwrap()
+        else {
+            panic!("Last byte of a partial write is pending")
+        };
+        assert_eq!(buf, [1, 2, 3]);
+
+        let mut buf = [0; 4];
+        let (2, WriteResult::Complete(2)) = handler.handle_part_counted(&mut buf).await.unwrap()
+        else {
+            panic!("All bytes of a complete write are acknowledged")
+        };
+        assert_eq!(buf[..2], [4, 5]);
+
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn read_continuation() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        let mut response = [0; 6];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [1, 2, 3, 4, 0xFF, 0xFF]);
+
+        let mut response = [0; 2];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [1, 2]);
+    };
+
+    let target = async move {
+        for expected in [6, 2] {
+            let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+                panic!()
+            };
+            let size = ReadContinuation::new(handler)
+                .then(&[1])
+                .await
+                .unwrap()
+                .then(&[2, 3])
+                .await
+                .unwrap()
+                .then(&[4])
+                .await
+                .unwrap()
+                .finish(0xFF)
+                .await
+                .unwrap();
+            assert_eq!(size, expected);
+            assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        }
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn general_call() {
+    let (mut c, mut t) = simulator();
+    t.set_address(ADDR);
+
+    let control = async move {
+        // Not delivered without general call enabled
+        let result = c.general_call(&[0x06]).await.unwrap_err();
+        assert_eq!(
+            result,
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+        );
+
+        c.write(A7, &[0x01]).await.unwrap();
+        c.general_call(&[0x06]).await.unwrap();
+    };
+
+    let target = async move {
+        assert!(t.addresses().eq([ADDR]));
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buf = [0];
+        handler.handle_complete(&mut buf).await.unwrap();
+        assert_eq!(buf, [0x01]);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        t.set_general_call(true);
+        assert!(t.addresses().eq([AnyAddress::Seven(0), ADDR]));
+
+        let Transaction::Write { address, handler } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(address, AnyAddress::Seven(0));
+        handler.handle_complete(&mut buf).await.unwrap();
+        assert_eq!(buf, [0x06]);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn step_mode() {
+    let (mut c, mut t) = simulator();
+    let stepper = t.step_mode();
+    let received = RefCell::new(Vec::new());
+
+    let control = async {
+        stepper.step();
+        c.write(A7, &[1]).await.unwrap();
+        assert_eq!(*received.borrow(), [1]);
+
+        // The target does not get the transaction until stepped
+        let step = async {
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            assert_eq!(*received.borrow(), [1]);
+            stepper.step();
+        };
+        let (result, ()) = tokio::join!(c.write(A7, &[2]), step);
+        result.unwrap();
+        assert_eq!(*received.borrow(), [1, 2]);
+    };
+
+    let target = async {
+        for _ in 0..2 {
+            let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+                panic!()
+            };
+            let mut buf = [0];
+            handler.handle_complete(&mut buf).await.unwrap();
+            received.borrow_mut().push(buf[0]);
+            assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        }
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn ops_remaining() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        let mut response = [0; 2];
+        c.write_read(A7, &[1], &mut response).await.unwrap();
+        assert_eq!(response, [2, 3]);
+    };
+
+    let target = async move {
+        assert_eq!(t.ops_remaining(), None);
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0]).await.unwrap();
+        assert_eq!(t.ops_remaining(), Some(1));
+
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&[2, 3], 0xFF).await.unwrap();
+        assert_eq!(t.ops_remaining(), Some(0));
+
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        assert_eq!(t.ops_remaining(), None);
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn consume() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        c.write(A7, &[0; 100]).await.unwrap();
+
+        let mut response = [0; 3];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [0xAA; 3]);
+    };
+
+    let target = async move {
+        for _ in 0..2 {
+            t.listen().await.unwrap().consume(0xAA).await.unwrap();
+            assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        }
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn empty_write() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        c.write(A7, &[]).await.unwrap();
+
+        let result = c.write(A7, &[]).await.unwrap_err();
+        assert_eq!(
+            result,
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+        );
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_empty().await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        drop(handler);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+/// Read handler providing at most four overrun characters
+struct CappedRead<R>(R);
+
+impl<R: AsyncReadTransaction> AsyncReadTransaction for CappedRead<R> {
+    type Error = R::Error;
+
+    const MAX_OVERRUN: usize = 4;
+
+    async fn handle_part(self, buffer: &[u8]) -> Result<ReadResult<Self>, Self::Error> {
+        Ok(match self.0.handle_part(buffer).await? {
+            ReadResult::Complete(size) => ReadResult::Complete(size),
+            ReadResult::Partial(inner) => ReadResult::Partial(Self(inner)),
+        })
+    }
+}
+
+#[tokio::test]
+async fn max_overrun() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        let mut response = [0; 10];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(
+            response,
+            [1, 2, 0xAA, 0xAA, 0xAA, 0xAA, 0x2A, 0x2A, 0x2A, 0x2A]
+        );
+    };
+
+    let target = async move {
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let size = CappedRead(handler)
+            .handle_complete(&[1, 2], 0xAA)
+            .await
+            .unwrap();
+        assert_eq!(size, 6);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn write_restart_write() {
+    let (mut c, mut t) = simulator();
+    // A hub responding to both 0x40 and 0x41
+    t.set_address_mask(AnyAddress::Seven(0x40), 0x7E);
+
+    let control = async move {
+        c.write_restart_write(0x40_u8, &[1], 0x41, &[2, 3])
+            .await
+            .unwrap();
+
+        let result = c
+            .write_restart_write(0x40_u8, &[4], 0x50, &[5])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            result,
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+        );
+    };
+
+    let target = async move {
+        let Transaction::Write { address, handler } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(address, AnyAddress::Seven(0x40));
+        let mut buf = [0; 2];
+        assert_eq!(handler.handle_complete(&mut buf).await.unwrap(), 1);
+        assert_eq!(buf[0], 1);
+        // The bus is still held for the second write
+        assert_eq!(t.ops_remaining(), Some(1));
+
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        let Transaction::Write { address, handler } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(address, AnyAddress::Seven(0x41));
+        assert_eq!(handler.handle_complete(&mut buf).await.unwrap(), 2);
+        assert_eq!(buf, [2, 3]);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        // Restarting to an address the hub does not respond to
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut buf).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        assert_eq!(t.ops_remaining(), None);
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn tap() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        c.write(A7, &[1, 2, 3]).await.unwrap();
+
+        let mut response = [0; 2];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [4, 5]);
+    };
+
+    let target = async move {
+        let mut log = Vec::new();
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buf = [0; 2];
+        let WriteResult::Partial(handler) = handler
+            .handle_part_tap(&mut buf, |data| log.extend_from_slice(data))
+            .await
+            .unwrap()
+        else {
+            panic!()
+        };
+        let mut buf = [0; 4];
+        let WriteResult::Complete(1) = handler
+            .handle_part_tap(&mut buf, |data| log.extend_from_slice(data))
+            .await
+            .unwrap()
+        else {
+            panic!()
+        };
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        assert_eq!(log, [1, 2, 3]);
+
+        log.clear();
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let ReadResult::Complete(2) = handler
+            .handle_part_tap(&[4, 5, 6], |data| log.extend_from_slice(data))
+            .await
+            .unwrap()
+        else {
+            panic!()
+        };
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        assert_eq!(log, [4, 5]);
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn register_access() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        // Combined format
+        let mut response = [0; 2];
+        c.write_read(A7, &[0x10], &mut response).await.unwrap();
+        assert_eq!(response, [0x10, 0x11]);
+
+        // A stop between setting the pointer and reading
+        c.write(A7, &[0x20]).await.unwrap();
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [0xFF, 0xFF]);
+
+        c.write(A7, &[0x30, 1, 2]).await.unwrap();
+    };
+
+    let target = async move {
+        let mut listener = RegisterAccessListener::new(ADDR, RegisterAddressWidth::OneByte);
+        let mut buf = [0; 4];
+
+        let RegisterAccess::Pointer { register: 0x10 } =
+            listener.listen(&mut t, &mut buf).await.unwrap()
+        else {
+            panic!()
+        };
+        let RegisterAccess::Read {
+            register: 0x10,
+            handler,
+        } = listener.listen(&mut t, &mut buf).await.unwrap()
+        else {
+            panic!()
+        };
+        handler.handle_complete(&[0x10, 0x11], 0xFF).await.unwrap();
+        let RegisterAccess::Other(Transaction::Deselect) =
+            listener.listen(&mut t, &mut buf).await.unwrap()
+        else {
+            panic!()
+        };
+
+        let RegisterAccess::Pointer { register: 0x20 } =
+            listener.listen(&mut t, &mut buf).await.unwrap()
+        else {
+            panic!()
+        };
+        let RegisterAccess::Other(Transaction::Deselect) =
+            listener.listen(&mut t, &mut buf).await.unwrap()
+        else {
+            panic!()
+        };
+        let RegisterAccess::Other(Transaction::Read { handler, .. }) =
+            listener.listen(&mut t, &mut buf).await.unwrap()
+        else {
+            panic!()
+        };
+        handler.handle_complete(&[], 0xFF).await.unwrap();
+        let RegisterAccess::Other(Transaction::Deselect) =
+            listener.listen(&mut t, &mut buf).await.unwrap()
+        else {
+            panic!()
+        };
+
+        let RegisterAccess::Write {
+            register: 0x30,
+            size: 2,
+        } = listener.listen(&mut t, &mut buf).await.unwrap()
+        else {
+            panic!()
+        };
+        assert_eq!(buf[..2], [1, 2]);
+        let RegisterAccess::Other(Transaction::Deselect) =
+            listener.listen(&mut t, &mut buf).await.unwrap()
+        else {
+            panic!()
+        };
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test(start_paused = true)]
+async fn data_ready() {
+    let (mut c, mut t) = simulator();
+    let ready = Arc::new(AtomicBool::new(true));
+    t.set_data_ready(Some(ready.clone()));
+
+    let control = async move {
+        // Start a conversion, taking 3ms
+        c.write(A7, &[0x01]).await.unwrap();
+
+        let mut naks = 0;
+        let mut response = [0; 2];
+        while let Err(e) = c.read(A7, &mut response).await {
+            assert_eq!(e, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+            naks += 1;
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        assert_eq!(naks, 2);
+        assert_eq!(response, [0x12, 0x34]);
+    };
+
+    let target = async move {
+        loop {
+            match t.listen().await.unwrap() {
+                Transaction::Deselect => {}
+                Transaction::Write { handler, .. } => {
+                    handler.handle_complete(&mut [0]).await.unwrap();
+                    ready.store(false, Ordering::Relaxed);
+                    let ready = ready.clone();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(Duration::from_millis(3)).await;
+                        ready.store(true, Ordering::Relaxed);
+                    });
+                }
+                Transaction::Read { handler, .. } => {
+                    if handler.handle_complete(&[0x12, 0x34], 0xFF).await.is_ok() {
+                        break;
+                    }
+                }
+            }
+        }
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn ack_policy() {
+    let (mut c, mut t) = simulator();
+    t.set_ack_policy(|address, direction| {
+        address != AnyAddress::Seven(0x20) || direction == Direction::Read
+    });
+
+    let control = async move {
+        let result = c.write(0x20_u8, &[1]).await.unwrap_err();
+        assert_eq!(
+            result,
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+        );
+
+        let mut response = [0; 1];
+        c.read(0x20_u8, &mut response).await.unwrap();
+        assert_eq!(response, [2]);
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let result = handler.handle_complete(&mut [0]).await.unwrap_err();
+        assert_eq!(
+            result,
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+        );
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&[2], 0xFF).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        assert_eq!(t.stats().address_naks, 1);
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn transaction_log() {
+    let (mut c, t) = simulator();
+    let mut t = LoggingTarget::<_, 3>::new(t);
+
+    let control = async move {
+        c.write(0x10_u8, &[1]).await.unwrap();
+        let mut response = [0];
+        c.read(0x11_u8, &mut response).await.unwrap();
+    };
+
+    let target = async {
+        t.listen().await.unwrap().consume(0xFF).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        t.listen().await.unwrap().consume(0xFF).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+
+    // The first write has been evicted
+    let log = t.log();
+    assert_eq!(log.len(), 3);
+    assert!(log.iter().eq(&[
+        TransactionTag::Deselect,
+        TransactionTag::Read(AnyAddress::Seven(0x11)),
+        TransactionTag::Deselect,
+    ]));
+}
+
+#[tokio::test]
+async fn ten_bit_write_read() {
+    let (mut c, mut t) = simulator();
+    t.set_address(AnyAddress::Ten(0x2A5));
+
+    let control = async move {
+        let mut response = [0; 2];
+        c.write_read_ten_bit(0x2A5, &[1], &mut response)
+            .await
+            .unwrap();
+        assert_eq!(response, [2, 3]);
+    };
+
+    let target = async move {
+        let Transaction::Write { address, handler } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(address, AnyAddress::Ten(0x2A5));
+        let mut buf = [0];
+        handler.handle_complete(&mut buf).await.unwrap();
+        assert_eq!(buf, [1]);
+
+        let Transaction::Read { address, handler } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(address, AnyAddress::Ten(0x2A5));
+        handler.handle_complete(&[2, 3], 0xFF).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn reject_first_byte() {
+    let (mut c, mut t) = simulator();
+    t.record_transcript();
+
+    let control = async move {
+        let result = c.write(A7, &[0x99, 1, 2]).await.unwrap_err();
+        assert_eq!(result, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data));
+
+        // Without data there is nothing to reject
+        c.write(A7, &[]).await.unwrap();
+    };
+
+    let target = async {
+        for _ in 0..2 {
+            let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+                panic!()
+            };
+            handler.reject_first_byte().await.unwrap();
+            assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        }
+    };
+
+    tokio::join!(control, target);
+    assert_eq!(t.stats().data_naks, 1);
+    assert!(
+        t.transcript()
+            .contains("target: rejected 0x99\noutcome: NAK Data\n")
+    );
+    assert!(!t.transcript().contains("received"));
+}
+
+#[tokio::test]
+async fn reject_first_byte_default() {
+    let (mut c, t) = simulator();
+    // Uses the default implementation on top of handle_part
+    let mut t = LengthLimitedTarget::new(t, 4);
+
+    let control = async move {
+        let result = c.write(A7, &[0x99, 1, 2]).await.unwrap_err();
+        assert_eq!(result, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data));
+        c.write(A7, &[]).await.unwrap();
+    };
+
+    let target = async move {
+        for _ in 0..2 {
+            let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+                panic!()
+            };
+            handler.reject_first_byte().await.unwrap();
+            assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        }
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn transcript() {
+    let (mut c, mut t) = simulator();
+    t.set_address(ADDR);
+    t.record_transcript();
+
+    let control = async move {
+        let mut response = [0; 3];
+        c.write_read(A7, &[0x10], &mut response).await.unwrap();
+        c.write(0x50_u8, &[1]).await.unwrap_err();
+        c.write(A7, &[2]).await.unwrap_err();
+    };
+
+    let target = async {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0]).await.unwrap();
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let ReadResult::Partial(handler) = handler.handle_part(&[0xAB]).await.unwrap() else {
+            panic!()
+        };
+        drop(handler);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        drop(handler);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+
+    assert_eq!(
+        t.transcript(),
+        "\
+controller: transaction to Seven(66)
+  Seven(66) write [10]
+  Seven(66) read 3 bytes
+target: received [10]
+target: provided [ab]
+target: overrun, 2 bytes of 0x2a
+outcome: ACK
+  controller read [ab, 2a, 2a]
+controller: transaction to Seven(80)
+  Seven(80) write [01]
+outcome: NAK Address
+controller: transaction to Seven(66)
+  Seven(66) write [02]
+outcome: NAK Address
+"
+    );
+}
+
+#[tokio::test]
+async fn channel_target() {
+    let (mut c, t) = simulator();
+    let (events, mut received) = tokio::sync::mpsc::channel(4);
+    let registers = Registers::default();
+    let mut target = ChannelTarget::new(t, ADDR, events, registers.clone());
+    let stop = AtomicBool::new(false);
+
+    let control = async move {
+        c.write(A7, &[0x05, 1, 2]).await.unwrap();
+        let event = received.recv().await.unwrap();
+        assert_eq!(
+            event,
+            RegisterWrite {
+                register: RegAddr(0x05),
+                data: vec![1, 2]
+            }
+        );
+
+        assert_eq!(event.size(), ByteLen(2));
+
+        // The application decides what reads return
+        registers.lock().unwrap().insert(event.register, event.data);
+        let mut response = [0; 3];
+        c.write_read(A7, &[0x05], &mut response).await.unwrap();
+        assert_eq!(response, [1, 2, 0xFF]);
+    };
+
+    tokio::select! {
+        () = target.run(&stop) => panic!("target stopped"),
+        () = control => {}
+    }
+}
+
+#[tokio::test]
+async fn write_then_empty_read() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        c.write_read(A7, &[1, 2], &mut []).await.unwrap();
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buf = [0; 4];
+        assert_eq!(handler.handle_complete(&mut buf).await.unwrap(), 2);
+        assert_eq!(t.ops_remaining(), Some(1));
+
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(handler.handle_complete(&[3, 4], 0xFF).await.unwrap(), 0);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn multiplex_target() {
+    let (mut c, t) = simulator();
+    let mut target = MultiplexTarget::new(t);
+    let mut sensor = target.handle(AnyAddress::Seven(0x10));
+    let mut display = target.handle(AnyAddress::Seven(0x11));
+    sensor.set_read_data([0x12, 0x34]);
+    let stop = AtomicBool::new(false);
+
+    let control = async move {
+        c.write(0x11u8, b"hi").await.unwrap();
+        c.write(0x10u8, &[0x01]).await.unwrap();
+        let mut reading = [0; 3];
+        c.read(0x10u8, &mut reading).await.unwrap();
+        assert_eq!(reading, [0x12, 0x34, 0xFF]);
+        assert_eq!(
+            c.write(0x12u8, &[0]).await.unwrap_err(),
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+        );
+    };
+
+    let sensor = async move {
+        assert_eq!(sensor.next().await, Some(AddressEvent::Write(vec![0x01])));
+        assert_eq!(sensor.next().await, Some(AddressEvent::Read(3)));
+    };
+
+    let display = async move {
+        assert_eq!(
+            display.next().await,
+            Some(AddressEvent::Write(b"hi".to_vec()))
+        );
+    };
+
+    tokio::select! {
+        () = target.run(&stop) => panic!("target stopped"),
+        _ = async { tokio::join!(control, sensor, display) } => {}
+    }
+}
+
+#[tokio::test]
+async fn strict_accept() {
+    let (mut c, mut t) = simulator();
+    t.set_strict_accept(true);
+
+    let control = async move {
+        let err = c.write(A7, &[1, 2]).await.unwrap_err();
+        assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        c.write(A7, &[1, 2]).await.unwrap();
+    };
+
+    let target = async move {
+        // Forgetting to accept does not acknowledge the address
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buf = [0; 2];
+        let err = handler.handle_complete(&mut buf).await.unwrap_err();
+        assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let handler = handler.accept().unwrap();
+        assert_eq!(handler.handle_complete(&mut buf).await.unwrap(), 2);
+        assert_eq!(buf, [1, 2]);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Lifecycle {
+    AddressAck(AnyAddress, Direction),
+    DataAck(usize),
+    Nack(NoAcknowledgeSource),
+    Complete(usize),
+}
+
+struct Recorder(Arc<Mutex<Vec<Lifecycle>>>);
+
+impl SimObserver for Recorder {
+    fn on_address_ack(&mut self, address: AnyAddress, direction: Direction) {
+        let event = Lifecycle::AddressAck(address, direction);
+        self.0.lock().unwrap().push(event);
+    }
+
+    fn on_data_ack(&mut self, len: usize) {
+        self.0.lock().unwrap().push(Lifecycle::DataAck(len));
+    }
+
+    fn on_nack(&mut self, source: NoAcknowledgeSource) {
+        self.0.lock().unwrap().push(Lifecycle::Nack(source));
+    }
+
+    fn on_complete(&mut self, size: usize) {
+        self.0.lock().unwrap().push(Lifecycle::Complete(size));
+    }
+}
+
+#[tokio::test]
+async fn observer() {
+    let (mut c, mut t) = simulator();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    t.set_observer(Recorder(events.clone()));
+
+    let control = async move {
+        let mut response = [0; 3];
+        c.write_read(A7, &[0x10], &mut response).await.unwrap();
+        assert_eq!(response, [1, 2, 0x2a]);
+        let err = c.write(A7, &[1]).await.unwrap_err();
+        assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(handler.handle_complete(&mut [0; 4]).await.unwrap(), 1);
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let ReadResult::Partial(handler) = handler.handle_part(&[1, 2]).await.unwrap() else {
+            panic!()
+        };
+        drop(handler);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        drop(handler);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            Lifecycle::AddressAck(ADDR, Direction::Write),
+            Lifecycle::DataAck(1),
+            Lifecycle::Complete(1),
+            Lifecycle::AddressAck(ADDR, Direction::Read),
+            Lifecycle::DataAck(2),
+            Lifecycle::Complete(3),
+            Lifecycle::Nack(NoAcknowledgeSource::Address),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn observer_exact_read() {
+    let (mut c, mut t) = simulator();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    t.set_observer(Recorder(events.clone()));
+
+    let control = async move {
+        let mut response = [0; 2];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [1, 2]);
+    };
+
+    let target = async move {
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(handler.handle_complete(&[1, 2], 0xff).await.unwrap(), 2);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            Lifecycle::AddressAck(ADDR, Direction::Read),
+            Lifecycle::DataAck(2),
+            Lifecycle::Complete(2),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn clock_range() {
+    let (mut c, mut t) = simulator();
+    t.set_clock_range(Some((10_000, 100_000)));
+    assert_eq!(t.clock_range(), Some((10_000, 100_000)));
+
+    let control = async move {
+        c.set_clock(Some(400_000));
+        let err = c.write(A7, &[1]).await.unwrap_err();
+        assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+
+        c.set_clock(Some(100_000));
+        c.write(A7, &[2]).await.unwrap();
+    };
+
+    let target = async move {
+        // The too fast transaction never shows up
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buf = [0; 1];
+        handler.handle_complete(&mut buf).await.unwrap();
+        assert_eq!(buf, [2]);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        assert_eq!(t.stats().address_naks, 1);
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test(start_paused = true)]
+async fn stretch() {
+    let (mut c, mut t) = simulator();
+    t.set_stretch(Duration::from_millis(10), Duration::from_millis(1));
+
+    let control = async move {
+        let start = Instant::now();
+        c.write(A7, &[1, 2, 3, 4]).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(4));
+
+        let start = Instant::now();
+        c.read(A7, &mut [0; 4]).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(40));
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0; 4]).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&[1, 2, 3, 4], 0xFF).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test(start_paused = true)]
+async fn stretch_limit() {
+    let (mut c, mut t) = simulator();
+    t.set_stretch(Duration::from_millis(10), Duration::from_millis(1));
+    c.set_max_stretch(Some(Duration::from_millis(5)));
+
+    let control = async move {
+        // The writes are fast enough
+        c.write(A7, &[1, 2]).await.unwrap();
+
+        let start = Instant::now();
+        let result = c.read(A7, &mut [0; 4]).await;
+        assert_eq!(result, Err(ErrorKind::Other));
+        assert_eq!(start.elapsed(), Duration::from_millis(5));
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0; 2]).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let result = handler.handle_complete(&[1, 2, 3, 4], 0xFF).await;
+        assert_eq!(result, Err(ErrorKind::Bus));
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn split_write_read() {
+    let (mut c, mut t) = simulator();
+    c.set_split_write_read(true);
+
+    let control = async move {
+        let mut response = [0; 2];
+        c.write_read(A7, &[0x05], &mut response).await.unwrap();
+        // The STOP in between made the target forget the register
+        assert_eq!(response, [0, 0]);
+    };
+
+    let target = async move {
+        let mut pointer = None;
+        loop {
+            match t.listen().await {
+                Ok(Transaction::Write { handler, .. }) => {
+                    let mut register = [0];
+                    handler.handle_complete(&mut register).await.unwrap();
+                    pointer = Some(register[0]);
+                }
+                Ok(Transaction::Deselect) => pointer = None,
+                Ok(Transaction::Read { handler, .. }) => {
+                    let data = if pointer == Some(0x05) {
+                        [0xAB, 0xCD]
+                    } else {
+                        [0, 0]
+                    };
+                    handler.handle_complete(&data, 0xFF).await.unwrap();
+                }
+                Err(_) => return,
+            }
+        }
+    };
+
+    tokio::join!(control, target);
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum StorageError {
+    Busy,
+    Broken,
+}
+
+#[tokio::test]
+async fn retry_handler() {
+    let (mut c, mut t) = simulator();
+    let is_transient = |e: &StorageError| *e == StorageError::Busy;
+
+    let control = async move {
+        let mut response = [0; 2];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [1, 2]);
+
+        let err = c.read(A7, &mut response).await.unwrap_err();
+        assert_eq!(err, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+    };
+
+    let target = async move {
+        // A flaky store that succeeds on the second attempt
+        let mut attempts = 0;
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let size = RetryHandler::new(handler, 3, is_transient)
+            .read_from(
+                &mut [0; 2],
+                async |buf: &mut [u8]| {
+                    attempts += 1;
+                    if attempts < 2 {
+                        return Err(StorageError::Busy);
+                    }
+                    buf.copy_from_slice(&[1, 2]);
+                    Ok(2)
+                },
+                0xFF,
+            )
+            .await
+            .unwrap();
+        assert_eq!((size, attempts), (2, 2));
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        // Errors that are not transient are not retried
+        let mut attempts = 0;
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let err = RetryHandler::new(handler, 3, is_transient)
+            .read_from(
+                &mut [0; 2],
+                async |_: &mut [u8]| {
+                    attempts += 1;
+                    Err(StorageError::Broken)
+                },
+                0xFF,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err, RetryError::Storage(StorageError::Broken));
+        assert_eq!(attempts, 1);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn checksums() {
+    let (mut c, mut t) = simulator();
+    let xor = |bytes: &[u8]| bytes.iter().fold(0, |sum, byte| sum ^ byte);
+
+    let control = async move {
+        let mut response = [0; 3];
+        c.write_read(A7, &[1, 2, 4], &mut response).await.unwrap();
+        assert_eq!(response, [0x10, 0x20, 0x2a]);
+        response
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0; 4]).await.unwrap();
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let ReadResult::Partial(handler) = handler.handle_part(&[0x10, 0x20]).await.unwrap() else {
+            panic!()
+        };
+        drop(handler);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        t.stats()
+    };
+
+    let (response, stats) = tokio::join!(control, target);
+    assert_eq!(stats.write_checksum, xor(&[1, 2, 4]));
+    assert_eq!(stats.read_checksum, xor(&response));
+    assert_eq!(stats.read_checksum, 0x1a);
+}
+
+#[tokio::test]
+async fn channel_target_callbacks() {
+    let (mut c, t) = simulator();
+    let (events, _received) = tokio::sync::mpsc::channel(4);
+    let mut target = ChannelTarget::new(t, ADDR, events, Registers::default());
+    let stop = AtomicBool::new(false);
+
+    let mut counter = 0_u8;
+    target.set_on_read(0x10, move || {
+        counter = counter.wrapping_add(1);
+        vec![counter]
+    });
+    let written = Arc::new(Mutex::new(Vec::new()));
+    let log = written.clone();
+    target.set_on_write(RegAddr(0x11), move |data| {
+        log.lock().unwrap().extend_from_slice(data)
+    });
+
+    let control = async move {
+        for expected in 1..=3 {
+            let mut value = [0];
+            c.write_read(A7, &[0x10], &mut value).await.unwrap();
+            assert_eq!(value, [expected]);
+        }
+        c.write(A7, &[0x11, 7, 8]).await.unwrap();
+        // Let the target handle the write before checking it
+        c.write_read(A7, &[0x10], &mut [0]).await.unwrap();
+        assert_eq!(*written.lock().unwrap(), [7, 8]);
+    };
+
+    tokio::select! {
+        () = target.run(&stop) => panic!("target stopped"),
+        () = control => {}
+    }
+}
+
+#[tokio::test]
+async fn nack_storm() {
+    let (mut c, mut t) = simulator();
+    c.nack_storm(3);
+
+    let control = async move {
+        let mut attempts = 0;
+        let result = loop {
+            attempts += 1;
+            match c.write(A7, &[1]).await {
+                Err(ErrorKind::NoAcknowledge(_)) if attempts < 10 => continue,
+                result => break result,
+            }
+        };
+        result.unwrap();
+        assert_eq!(attempts, 4);
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buf = [0; 1];
+        handler.handle_complete(&mut buf).await.unwrap();
+        assert_eq!(buf, [1]);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        // The storm never reached the target
+        assert_eq!(t.stats().address_naks, 0);
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn channel_target_fifo() {
+    let (mut c, t) = simulator();
+    let (events, _received) = tokio::sync::mpsc::channel(4);
+    let mut target = ChannelTarget::new(t, ADDR, events, Registers::default());
+    let stop = AtomicBool::new(false);
+
+    let fifo = Fifo::default();
+    fifo.lock().unwrap().extend([vec![1], vec![2], vec![3]]);
+    target.set_fifo(RegAddr(0x20), fifo.clone(), vec![0]);
+
+    let control = async move {
+        for expected in [1, 2, 3, 0] {
+            let mut value = [0xAA];
+            c.write_read(A7, &[0x20], &mut value).await.unwrap();
+            assert_eq!(value, [expected]);
+        }
+        assert!(fifo.lock().unwrap().is_empty());
+    };
+
+    tokio::select! {
+        () = target.run(&stop) => panic!("target stopped"),
+        () = control => {}
+    }
+}
+
+#[tokio::test]
+async fn finish_with_tail() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        let mut response = [0; 6];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [1, 2, 0x5A, 0x5A, 0x5A, 0x5A]);
+    };
+
+    let target = async move {
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let ReadResult::Partial(handler) = handler.handle_part(&[1, 2]).await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(handler.finish_with(0x5A).await.unwrap(), 4);
+
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        t
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn dropped_target() {
+    let (mut c, t) = simulator();
+    drop(t);
+
+    let result = tokio::time::timeout(Duration::from_secs(1), c.write(A7, &[1, 2])).await;
+    assert_eq!(result.unwrap(), Err(ErrorKind::Other));
+}
+
+#[tokio::test]
+async fn idle_target_timeout() {
+    let (mut c, _t) = simulator();
+    c.set_target_timeout(Some(Duration::from_millis(10)));
+
+    // The target never listens
+    assert_eq!(c.write(A7, &[1, 2]).await, Err(ErrorKind::Other));
+
+    let mut blocking = c.clone();
+    let result = std::thread::spawn(move || {
+        embedded_hal_i2c::SyncI2cController::write(&mut blocking, A7, &[3])
+    })
+    .join()
+    .unwrap();
+    assert_eq!(result, Err(ErrorKind::Other));
+}
+
+#[tokio::test]
+async fn channel_target_defaults() {
+    let (mut c, t) = simulator();
+    let (events, _received) = tokio::sync::mpsc::channel(4);
+    let registers = Registers::default();
+    let mut target = ChannelTarget::new(t, ADDR, events, registers.clone())
+        .with_defaults(&[(RegAddr(0x00), &[0x5A, 0x01]), (RegAddr(0x0F), &[0x80])]);
+    let stop = AtomicBool::new(false);
+    assert_eq!(registers.lock().unwrap().len(), 2);
+
+    let control = async move {
+        let mut id = [0; 2];
+        c.write_read(A7, &[0x00], &mut id).await.unwrap();
+        assert_eq!(id, [0x5A, 0x01]);
+
+        let mut config = [0];
+        c.write_read(A7, &[0x0F], &mut config).await.unwrap();
+        assert_eq!(config, [0x80]);
+    };
+
+    tokio::select! {
+        () = target.run(&stop) => panic!("target stopped"),
+        () = control => {}
+    }
+}
+
+#[tokio::test]
+async fn progress_controller() {
+    let (c, mut t) = simulator();
+    let progress = Arc::new(AtomicUsize::new(0));
+    let mut c = ProgressController::new(c, progress.clone());
+    let seen = progress.clone();
+
+    let control = async move {
+        let data = vec![0x55; 4096];
+        c.write(A7, &data).await.unwrap();
+        assert_eq!(progress.load(Ordering::Relaxed), 4096);
+
+        let mut response = [0; 16];
+        c.write_read(A7, &[0], &mut response).await.unwrap();
+        assert_eq!(progress.load(Ordering::Relaxed), 4096 + 1 + 16);
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buffer = vec![0; 4096];
+        assert_eq!(handler.handle_complete(&mut buffer).await.unwrap(), 4096);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0]).await.unwrap();
+        // Only complete transactions are counted, not the operations within
+        assert_eq!(seen.load(Ordering::Relaxed), 4096);
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&[1; 16], 0xFF).await.unwrap();
+        assert_eq!(seen.load(Ordering::Relaxed), 4096);
+
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        t
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn drain_write() {
+    let (mut c, mut t) = simulator();
+    let data: Vec<u8> = (0..100).collect();
+    let sent = data.clone();
+
+    let control = async move {
+        c.write(A7, &sent).await.unwrap();
+        c.write(A7, &sent[..96]).await.unwrap();
+    };
+
+    let target = async move {
+        // 12 full chunks, one with the last 4 bytes; then exactly 12 full chunks
+        for (len, expected_chunks) in [(100, 13), (96, 12)] {
+            let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+                panic!()
+            };
+            let mut chunks = 0;
+            let mut received = Vec::new();
+            let total = handler
+                .drain_write(&mut [0; 8], |chunk| {
+                    chunks += 1;
+                    received.extend_from_slice(chunk);
+                })
+                .await
+                .unwrap();
+            assert_eq!(total, len);
+            assert_eq!(received, data[..len]);
+            assert_eq!(chunks, expected_chunks);
+
+            assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        }
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn seven_bit_target() {
+    let (mut c, t) = simulator();
+    let mut t = SevenBitTarget::new(t);
+
+    let control = async move {
+        c.write(A7, &[1, 2]).await.unwrap();
+        let result = c.write(0x142_u16, &[3, 4]).await;
+        assert_eq!(
+            result,
+            Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address))
+        );
+        let mut response = [0; 1];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [5]);
+    };
+
+    let target = async move {
+        let mut received = Vec::new();
+        loop {
+            match t.listen().await {
+                Ok(Transaction::Deselect) => {}
+                Ok(Transaction::Write { address, handler }) => {
+                    let AnyAddress::Seven(address) = address else {
+                        panic!("only 7-bit addresses expected")
+                    };
+                    assert_eq!(address, A7);
+                    let mut buf = [0; 2];
+                    let size = handler.handle_complete(&mut buf).await.unwrap();
+                    received.extend_from_slice(&buf[..size]);
+                }
+                Ok(Transaction::Read { address, handler }) => {
+                    assert_eq!(address, ADDR);
+                    handler.handle_complete(&[5], 0xFF).await.unwrap();
+                }
+                // The controller is gone
+                Err(_) => break,
+            }
+        }
+        assert_eq!(received, [1, 2]);
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn snapshot_restore() {
+    let (mut c, mut t) = simulator();
+    t.record_transcript();
+    let snapshot = t.snapshot();
+    t.set_address(ADDR);
+
+    let control = async move {
+        c.write(A7, &[1]).await.unwrap();
+        let result = c.read(A7, &mut [0]).await;
+        assert_eq!(
+            result,
+            Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address))
+        );
+        // Only acknowledged once the address mask is restored
+        c.write(0x10_u8, &[2]).await.unwrap();
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0]).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        drop(handler);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        assert_eq!(t.stats().address_naks, 1);
+        assert!(!t.transcript().is_empty());
+
+        t.restore(snapshot);
+        assert_eq!(t.stats(), SimStats::default());
+        assert_eq!(t.transcript(), "");
+
+        let Transaction::Write { address, handler } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(address, AnyAddress::Seven(0x10));
+        handler.handle_complete(&mut [0]).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn over_read_idle_level() {
+    let (mut c, mut t) = simulator();
+    t.set_over_read(OverRead::Idle(0xFF));
+
+    let control = async move {
+        let mut response = [0; 4];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [1, 0xFF, 0xFF, 0xFF]);
+    };
+
+    let target = async move {
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let ReadResult::Partial(handler) = handler.handle_part(&[1]).await.unwrap() else {
+            panic!()
+        };
+        drop(handler);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn process_call() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        assert_eq!(c.process_call(A7, 0x30, 0x1234).await.unwrap(), 0x1235);
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut request = [0; 3];
+        assert_eq!(handler.handle_complete(&mut request).await.unwrap(), 3);
+        assert_eq!(request, [0x30, 0x34, 0x12]);
+        let value = u16::from_le_bytes([request[1], request[2]]);
+
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let response = (value + 1).to_le_bytes();
+        handler.handle_complete(&response, 0xFF).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn send_receive_byte() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        c.send_byte(A7, 0x5a).await.unwrap();
+        assert_eq!(c.receive_byte(A7).await.unwrap(), 0xa5);
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut byte = [0; 2];
+        assert_eq!(handler.handle_complete(&mut byte).await.unwrap(), 1);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        // Answer with the inverse of the byte sent
+        assert_eq!(handler.handle_complete(&[!byte[0]], 0xFF).await.unwrap(), 1);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn channel_target_block_read() {
+    let (mut c, t) = simulator();
+    let (events, _received) = tokio::sync::mpsc::channel(4);
+    let registers = Registers::default();
+    registers
+        .lock()
+        .unwrap()
+        .insert(RegAddr(0x40), b"hello".to_vec());
+    let mut target = ChannelTarget::new(t, ADDR, events, registers);
+    target.set_block_read(RegAddr(0x40));
+    let stop = AtomicBool::new(false);
+
+    let control = async move {
+        // Read up to the maximum block size, as the length is not known up front
+        let mut block = [0; 33];
+        c.write_read(A7, &[0x40], &mut block).await.unwrap();
+        let count = usize::from(block[0]);
+        assert_eq!(count, 5);
+        assert_eq!(&block[1..=count], b"hello");
+        assert_eq!(block[count + 1], 0xFF);
+    };
+
+    tokio::select! {
+        () = target.run(&stop) => panic!("target stopped"),
+        () = control => {}
+    }
+}
+
+#[tokio::test]
+async fn echo_mode() {
+    let (mut c, mut t) = simulator();
+    t.set_echo(true);
+
+    let control = async move {
+        c.write(A7, &[1, 2, 3]).await.unwrap();
+        let mut response = [0; 5];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [1, 2, 3, 0x2a, 0x2a]);
+
+        let mut response = [0; 1];
+        c.write_read(A7, &[9], &mut response).await.unwrap();
+        assert_eq!(response, [9]);
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0; 4]).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        // The read is answered without being reported, only its end is
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0; 4]).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn has_pending() {
+    let (mut c, mut t) = simulator();
+    assert!(!t.has_pending());
+
+    let control = async move {
+        c.write_read(A7, &[1], &mut [0]).await.unwrap();
+    };
+
+    let target = async move {
+        // Let the controller queue its transaction
+        tokio::task::yield_now().await;
+        assert!(t.has_pending());
+        assert_eq!(t.pending_address(), None);
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0]).await.unwrap();
+        assert!(t.has_pending());
+        assert_eq!(t.pending_address(), Some(ADDR));
+
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&[2], 0xFF).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        assert!(!t.has_pending());
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn write_truncated() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        c.write_truncated(A7, &[0x10, 1, 2], 2).await.unwrap();
+        c.write(A7, &[0x10, 3, 4]).await.unwrap();
+    };
+
+    let target = async move {
+        let mut applied = Vec::new();
+        loop {
+            match t.listen().await {
+                Ok(Transaction::Write { handler, .. }) => {
+                    let mut command = [0; 3];
+                    // Commands are three bytes, discard anything shorter
+                    if handler.handle_complete(&mut command).await.unwrap() == command.len() {
+                        applied.push(command);
+                    }
+                }
+                Ok(Transaction::Deselect) => {}
+                Ok(Transaction::Read { .. }) => panic!("unexpected read"),
+                Err(_) => break,
+            }
+        }
+        assert_eq!(applied, [[0x10, 3, 4]]);
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn transaction_count() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        c.write(A7, &[1]).await.unwrap();
+        c.read(A7, &mut [0]).await.unwrap();
+        c.write_read(A7, &[2], &mut [0]).await.unwrap();
+    };
+
+    let target = async move {
+        let mut buf = [0; 4];
+        // Serve until the controller is dropped
+        loop {
+            match t.listen().await {
+                Ok(Transaction::Write { handler, .. }) => {
+                    handler.handle_complete(&mut buf).await.unwrap();
+                }
+                Ok(Transaction::Read { handler, .. }) => {
+                    handler.handle_complete(&[7], 0xFF).await.unwrap();
+                }
+                Ok(Transaction::Deselect) => {}
+                Err(_) => break,
+            }
+        }
+        t
+    };
+
+    let ((), t) = tokio::join!(control, target);
+    t.assert_transaction_count(3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn read_async_source() {
+    let (mut c, mut t) = simulator();
+    let data: [u8; 20] = std::array::from_fn(|n| n as u8);
+
+    let control = async move {
+        let mut response = [0; 10];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, data[..10]);
+
+        let mut response = [0; 24];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response[..20], data);
+        assert_eq!(response[20..], [0x2a; 4]);
+
+        let mut response = [0; 20];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, data);
+    };
+
+    let target = async move {
+        for (expected, fetches) in [(10, 3), (20, 6), (20, 5)] {
+            let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+                panic!()
+            };
+            // Flash handing out at most 4 bytes per slow fetch
+            let mut offset = 0;
+            let mut count = 0;
+            let refill = async |buf: &mut [u8]| {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                count += 1;
+                let len = buf.len().min(data.len() - offset);
+                buf[..len].copy_from_slice(&data[offset..offset + len]);
+                offset += len;
+                len
+            };
+            let size = handler.handle_async(&mut [0; 4], refill).await.unwrap();
+            // The overrun past the end of the data is not counted
+            assert_eq!(size, expected);
+            assert_eq!(count, fetches);
+            assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+        }
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn device_id() {
+    let (mut c, mut t) = simulator();
+    let id = DeviceId {
+        manufacturer: 0x123,
+        part: 0x1A5,
+        revision: 5,
+    };
+    t.set_address(ADDR);
+    t.set_device_id(Some(id));
+
+    let control = async move {
+        assert_eq!(c.read_device_id(A7).await, Ok(id));
+        let mut raw = [0; 3];
+        c.write_read(DEVICE_ID_ADDRESS, &[A7 << 1], &mut raw)
+            .await
+            .unwrap();
+        assert_eq!(raw, [0x12, 0x3D, 0x2D]);
+
+        let nak = Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data));
+        assert_eq!(c.read_device_id(0x10).await, nak);
+    };
+
+    let target = async move {
+        // Device ID commands are answered without being reported
+        assert_eq!(t.listen().await.err(), Some(ErrorKind::Other));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn channel_target_read_length() {
+    let (mut c, t) = simulator();
+    let (events, _received) = tokio::sync::mpsc::channel(4);
+    let mut target = ChannelTarget::new(t, ADDR, events, Registers::default())
+        .with_defaults(&[(RegAddr(0x10), &[1, 2])]);
+    target.set_read_length(RegAddr(0x10), ByteLen(2));
+    let stop = AtomicBool::new(false);
+
+    let control = async move {
+        let mut value = [0; 2];
+        c.write_read(A7, &[0x10], &mut value).await.unwrap();
+        let mut short = [0; 1];
+        c.write_read(A7, &[0x10], &mut short).await.unwrap();
+        // Make sure the target finished handling the read
+        c.write(A7, &[0x20]).await.unwrap();
+    };
+
+    tokio::select! {
+        () = target.run(&stop) => panic!("target stopped"),
+        () = control => {}
+    }
+    assert_eq!(
+        target.read_length_mismatches(),
+        [ReadLengthMismatch {
+            register: RegAddr(0x10),
+            expected: ByteLen(2),
+            actual: ByteLen(1),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn finish_write() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        let data_nak = Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data));
+        let address_nak = Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        // Dropped, then finished explicitly
+        assert_eq!(c.write(A7, &[1, 2, 3]).await, data_nak);
+        assert_eq!(c.write(A7, &[1, 2, 3]).await, data_nak);
+        assert_eq!(c.write(A7, &[1]).await, address_nak);
+        assert_eq!(c.write(A7, &[1]).await, address_nak);
+    };
+
+    let target = async move {
+        let mut stats = Vec::new();
+        for finish in [false, true, false, true] {
+            let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+                panic!()
+            };
+            // Only take data on the first two writes
+            let handler = if stats.len() < 2 {
+                let WriteResult::Partial(handler) = handler.handle_part(&mut [0; 2]).await.unwrap()
+                else {
+                    panic!()
+                };
+                handler
+            } else {
+                handler
+            };
+            if finish {
+                assert_eq!(handler.finish().await, Ok(0));
+            } else {
+                drop(handler);
+            }
+            assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+            stats.push(t.stats());
+        }
+        // Finishing counts the same as dropping
+        assert_eq!(stats[1].data_naks - stats[0].data_naks, 1);
+        assert_eq!(stats[3].address_naks - stats[2].address_naks, 1);
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn write_pages_resume() {
+    let (mut c, mut t) = simulator();
+    let image: [u8; 40] = std::array::from_fn(|n| n as u8 + 1);
+
+    let control = async move {
+        let mut buffer = [0; 18];
+        let mut progress = Vec::new();
+        // The second page fails
+        let err = c
+            .write_pages(A7, 4, &image, 16, &mut buffer, |n| progress.push(n))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            PageWriteError {
+                error: ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+                written: 12,
+            }
+        );
+
+        let done = err.written;
+        c.write_pages(A7, 4 + done as u16, &image[done..], 16, &mut buffer, |n| {
+            progress.push(done + n)
+        })
+        .await
+        .unwrap();
+        assert_eq!(progress, [12, 28, 40]);
+    };
+
+    let target = async move {
+        let mut memory = [0; 64];
+        let mut pages = Vec::new();
+        loop {
+            match t.listen().await {
+                Ok(Transaction::Write { handler, .. }) => {
+                    let mut addr = [0; 2];
+                    let WriteResult::Partial(handler) =
+                        handler.handle_part(&mut addr).await.unwrap()
+                    else {
+                        panic!()
+                    };
+                    let start = usize::from(u16::from_be_bytes(addr));
+                    pages.push(start);
+                    if pages.len() == 2 {
+                        // Injected failure
+                        drop(handler);
+                        continue;
+                    }
+                    handler.handle_complete(&mut memory[start..]).await.unwrap();
+                }
+                Ok(Transaction::Deselect) => {}
+                Ok(Transaction::Read { .. }) => panic!("unexpected read"),
+                Err(_) => break,
+            }
+        }
+        // Page aligned, with the failed page retried
+        assert_eq!(pages, [4, 16, 16, 32]);
+        memory
+    };
+
+    let ((), memory) = tokio::join!(control, target);
+    assert_eq!(memory[..4], [0; 4]);
+    assert_eq!(memory[4..44], image);
+    assert_eq!(memory[44..], [0; 20]);
+}
+
+#[tokio::test]
+async fn in_transaction() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        c.write_read(A7, &[1], &mut [0]).await.unwrap();
+        let nak = Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown));
+        assert_eq!(c.write(A7, &[2]).await, nak);
+    };
+
+    let target = async move {
+        assert!(!t.in_transaction());
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&mut [0]).await.unwrap();
+        // The read is still to come
+        assert!(t.in_transaction());
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&[7], 0xFF).await.unwrap();
+        assert!(!t.in_transaction());
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        // A leaked handler leaves the transaction hanging
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        std::mem::forget(handler);
+        assert!(t.in_transaction());
+        t.power_cycle();
+        assert!(!t.in_transaction());
+    };
+
+    tokio::join!(control, target);
+}
+
+struct Contexts(Arc<Mutex<Vec<Option<u64>>>>);
+
+impl SimObserver for Contexts {
+    fn on_transaction(&mut self, _address: AnyAddress, context: Option<u64>) {
+        self.0.lock().unwrap().push(context);
+    }
+}
+
+#[tokio::test]
+async fn transaction_context() {
+    let (mut c, mut t) = simulator();
+    let contexts = Arc::new(Mutex::new(Vec::new()));
+    t.set_observer(Contexts(contexts.clone()));
+    t.record_transcript();
+
+    let control = async move {
+        c.transaction_tagged(A7, &mut [Operation::Write(&[1])], 7)
+            .await
+            .unwrap();
+        c.write(A7, &[2]).await.unwrap();
+        let mut buf = [0];
+        c.transaction_tagged(
+            A7,
+            &mut [Operation::Write(&[3]), Operation::Read(&mut buf)],
+            9,
+        )
+        .await
+        .unwrap();
+        // The tag is not sent on the bus
+        assert_eq!(buf, [3]);
+    };
+
+    let target = async move {
+        let mut last = [0];
+        loop {
+            match t.listen().await {
+                Ok(Transaction::Write { handler, .. }) => {
+                    handler.handle_complete(&mut last).await.unwrap();
+                }
+                Ok(Transaction::Read { handler, .. }) => {
+                    handler.handle_complete(&last, 0xFF).await.unwrap();
+                }
+                Ok(Transaction::Deselect) => {}
+                Err(_) => break,
+            }
+        }
+        t
+    };
+
+    let ((), t) = tokio::join!(control, target);
+    assert_eq!(*contexts.lock().unwrap(), [Some(7), None, Some(9)]);
+    let requests: Vec<_> = t
+        .transcript()
+        .lines()
+        .filter(|line| line.starts_with("controller:"))
+        .collect();
+    assert_eq!(
+        requests,
+        [
+            "controller: transaction to Seven(66), context 7",
+            "controller: transaction to Seven(66)",
+            "controller: transaction to Seven(66), context 9",
+        ]
+    );
+}
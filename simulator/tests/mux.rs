@@ -0,0 +1,61 @@
+use embedded_hal_i2c::{
+    AnyAddress, AsyncI2cController, AsyncI2cTarget, AsyncWriteTransaction, ErrorKind,
+    NoAcknowledgeSource, Transaction,
+};
+use simulator::mux::simulator_with_mux;
+use simulator::target::SimTarget;
+
+const MUX: u8 = 0x70;
+
+/// Collect the writes a target receives, until the mux is gone.
+async fn collect_writes(mut t: SimTarget) -> Vec<Vec<u8>> {
+    let mut writes = Vec::new();
+    loop {
+        match t.listen().await {
+            Ok(Transaction::Write { handler, .. }) => {
+                let mut buf = [0; 8];
+                let len = handler.handle_complete(&mut buf).await.unwrap();
+                writes.push(buf[..len].to_vec());
+            }
+            Ok(Transaction::Deselect) => {}
+            Ok(Transaction::Read { .. }) => panic!("unexpected read"),
+            Err(_) => return writes,
+        }
+    }
+}
+
+#[tokio::test]
+async fn channel_select() {
+    let (mut c, mut mux, targets) = simulator_with_mux(AnyAddress::Seven(MUX), 2);
+    let [mut first, mut second] = <[SimTarget; 2]>::try_from(targets).ok().unwrap();
+    first.set_address(AnyAddress::Seven(0x42));
+    second.set_address(AnyAddress::Seven(0x43));
+
+    let control = async move {
+        let nak = Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        // All channels are disabled initially
+        assert_eq!(c.write(0x42_u8, &[1]).await, nak);
+
+        c.write(MUX, &[0b01]).await.unwrap();
+        let mut control = [0];
+        c.read(MUX, &mut control).await.unwrap();
+        assert_eq!(control, [0b01]);
+        c.write(0x42_u8, &[2]).await.unwrap();
+        // Only reachable on the other channel
+        assert_eq!(c.write(0x43_u8, &[3]).await, nak);
+
+        c.write(MUX, &[0b10]).await.unwrap();
+        c.write(0x43_u8, &[4]).await.unwrap();
+        assert_eq!(c.write(0x42_u8, &[5]).await, nak);
+    };
+
+    let mux = async move {
+        mux.run().await;
+        assert_eq!(mux.control(), 0b10);
+    };
+
+    let ((), (), first, second) =
+        tokio::join!(control, mux, collect_writes(first), collect_writes(second));
+    assert_eq!(first, [vec![2]]);
+    assert_eq!(second, [vec![4]]);
+}
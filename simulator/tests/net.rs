@@ -0,0 +1,50 @@
+use embedded_hal_i2c::{
+    AnyAddress, AsyncI2cController, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction,
+    ErrorKind, NoAcknowledgeSource, Transaction,
+};
+use simulator::net::{TcpController, TcpTarget};
+use tokio::net::TcpListener;
+
+const A7: u8 = 0x42;
+
+#[tokio::test]
+async fn loopback_write_read() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mut c = TcpController::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut t = TcpTarget::new(stream);
+    t.sim().set_address(AnyAddress::Seven(A7));
+
+    let control = async move {
+        let mut response = [0; 4];
+        c.write_read(A7, &[1, 2], &mut response).await.unwrap();
+        assert_eq!(response, [5, 6, 7, 0xFF]);
+
+        // Errors are passed back too
+        let nak = Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        assert_eq!(c.write(0x10_u8, &[1]).await, nak);
+    };
+
+    let target = async move {
+        let Transaction::Write { address, handler } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        assert_eq!(address, AnyAddress::Seven(A7));
+        let mut buf = [0; 4];
+        assert_eq!(handler.handle_complete(&mut buf).await, Ok(2));
+        assert_eq!(buf[..2], [1, 2]);
+
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&[5, 6, 7], 0xFF).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        // The connection is closed once the controller is dropped
+        assert_eq!(t.listen().await.err(), Some(ErrorKind::Other));
+    };
+
+    tokio::join!(control, target);
+}
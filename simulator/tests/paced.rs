@@ -0,0 +1,55 @@
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_i2c::paced::PacedTarget;
+use embedded_hal_i2c::{
+    AsyncI2cController, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction, Transaction,
+};
+use simulator::simulator;
+use std::time::Duration;
+use tokio::time::Instant;
+
+const A7: u8 = 0x42;
+
+struct TokioDelay;
+
+impl DelayNs for TokioDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        tokio::time::sleep(Duration::from_nanos(ns.into())).await;
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn paced_delay_accumulates() {
+    let (mut c, t) = simulator();
+    let mut t = PacedTarget::new(t, TokioDelay, 1_000_000);
+
+    let control = async move {
+        let start = Instant::now();
+        let mut response = [0; 4];
+        c.read(A7, &mut response).await.unwrap();
+        assert_eq!(response, [1, 2, 3, 4]);
+        assert_eq!(start.elapsed(), Duration::from_millis(4));
+
+        let start = Instant::now();
+        c.write(A7, &[5, 6, 7]).await.unwrap();
+        // One more to learn the write ended
+        assert_eq!(start.elapsed(), Duration::from_millis(4));
+    };
+
+    let target = async move {
+        let Transaction::Read { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        handler.handle_complete(&[1, 2, 3, 4], 0xFF).await.unwrap();
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buffer = [0; 8];
+        assert_eq!(handler.handle_complete(&mut buffer).await.unwrap(), 3);
+        assert_eq!(buffer[..3], [5, 6, 7]);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
@@ -0,0 +1,128 @@
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_i2c::smbus::{SmbusError, SmbusTarget};
+use embedded_hal_i2c::{
+    AsyncI2cController, AsyncI2cTarget, AsyncReadTransaction, AsyncWriteTransaction, ErrorKind,
+    NoAcknowledgeSource, ReadResult, Transaction, WriteResult,
+};
+use simulator::simulator;
+use std::time::Duration;
+
+const A7: u8 = 0x42;
+
+struct TokioDelay;
+
+impl DelayNs for TokioDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        tokio::time::sleep(Duration::from_nanos(ns.into())).await;
+    }
+}
+
+/// Target whose handlers take `Duration` to process every buffer
+struct SlowTarget<T>(T, Duration);
+
+struct Slow<H>(H, Duration);
+
+impl<T: AsyncI2cTarget> AsyncI2cTarget for SlowTarget<T> {
+    type Error = T::Error;
+    type Read<'a>
+        = Slow<T::Read<'a>>
+    where
+        Self: 'a;
+    type Write<'a>
+        = Slow<T::Write<'a>>
+    where
+        Self: 'a;
+
+    async fn listen(
+        &mut self,
+    ) -> Result<Transaction<Self::Read<'_>, Self::Write<'_>>, Self::Error> {
+        let slow = self.1;
+        Ok(match self.0.listen().await? {
+            Transaction::Deselect => Transaction::Deselect,
+            Transaction::Read { address, handler } => Transaction::Read {
+                address,
+                handler: Slow(handler, slow),
+            },
+            Transaction::Write { address, handler } => Transaction::Write {
+                address,
+                handler: Slow(handler, slow),
+            },
+        })
+    }
+}
+
+impl<R: AsyncReadTransaction> AsyncReadTransaction for Slow<R> {
+    type Error = R::Error;
+
+    async fn handle_part(self, buffer: &[u8]) -> Result<ReadResult<Self>, Self::Error> {
+        let result = self.0.handle_part(buffer).await?;
+        tokio::time::sleep(self.1).await;
+        Ok(match result {
+            ReadResult::Complete(size) => ReadResult::Complete(size),
+            ReadResult::Partial(handler) => ReadResult::Partial(Slow(handler, self.1)),
+        })
+    }
+}
+
+impl<W: AsyncWriteTransaction> AsyncWriteTransaction for Slow<W> {
+    type Error = W::Error;
+
+    async fn handle_part(self, buffer: &mut [u8]) -> Result<WriteResult<Self>, Self::Error> {
+        let result = self.0.handle_part(buffer).await?;
+        tokio::time::sleep(self.1).await;
+        Ok(match result {
+            WriteResult::Complete(size) => WriteResult::Complete(size),
+            WriteResult::Partial(handler) => WriteResult::Partial(Slow(handler, self.1)),
+        })
+    }
+}
+
+#[tokio::test]
+async fn fast_handler_completes() {
+    let (mut c, t) = simulator();
+    let mut t = SmbusTarget::new(SlowTarget(t, Duration::ZERO), TokioDelay, 10_000);
+
+    let control = async move {
+        c.write(A7, &[1, 2]).await.unwrap();
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buf = [0; 4];
+        assert_eq!(handler.handle_complete(&mut buf).await.unwrap(), 2);
+        assert_eq!(buf[..2], [1, 2]);
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
+
+#[tokio::test]
+async fn slow_handler_times_out() {
+    let (mut c, t) = simulator();
+    let mut t = SmbusTarget::new(
+        SlowTarget(t, Duration::from_millis(100)),
+        TokioDelay,
+        10_000,
+    );
+
+    let control = async move {
+        let result = c.write(A7, &[1, 2, 3]).await.unwrap_err();
+        assert_eq!(result, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data));
+    };
+
+    let target = async move {
+        let Transaction::Write { handler, .. } = t.listen().await.unwrap() else {
+            panic!()
+        };
+        let mut buf = [0; 2];
+        let Err(SmbusError::Timeout) = handler.handle_part(&mut buf).await else {
+            panic!("Expected timeout")
+        };
+        assert!(matches!(t.listen().await.unwrap(), Transaction::Deselect));
+    };
+
+    tokio::join!(control, target);
+}
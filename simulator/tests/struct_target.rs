@@ -0,0 +1,58 @@
+use bytemuck::{Pod, Zeroable};
+use embedded_hal_i2c::struct_target::StructTarget;
+use embedded_hal_i2c::{AnyAddress, AsyncI2cController};
+use simulator::simulator;
+
+const A7: u8 = 0x42;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+struct Registers {
+    id: u8,
+    config: u8,
+    threshold: [u8; 2],
+    counter: u32,
+}
+
+#[tokio::test]
+async fn write_fields_read_struct() {
+    let (mut c, t) = simulator();
+    let initial = Registers {
+        id: 0x5a,
+        config: 0,
+        threshold: [0; 2],
+        counter: 0,
+    };
+
+    let control = async move {
+        // Update the fields one at a time
+        c.write(A7, &[1, 0x81]).await.unwrap();
+        c.write(A7, &[2, 0x34, 0x12]).await.unwrap();
+        c.write(A7, &[4, 1, 2, 3, 4]).await.unwrap();
+        // Past the end of the struct, ignored
+        c.write(A7, &[7, 0xAA, 0xBB]).await.unwrap();
+
+        let mut all = [0; 9];
+        c.write_read(A7, &[0], &mut all).await.unwrap();
+        assert_eq!(all, [0x5a, 0x81, 0x34, 0x12, 1, 2, 3, 0xAA, 0xFF]);
+    };
+
+    let target = async move {
+        let mut target = StructTarget::new(t, AnyAddress::Seven(A7), initial);
+        let mut buf = [0; 8];
+        // Ends when the controller is dropped
+        while target.handle(&mut buf).await.is_ok() {}
+        target.into_inner().1
+    };
+
+    let ((), registers) = tokio::join!(control, target);
+    assert_eq!(
+        registers,
+        Registers {
+            id: 0x5a,
+            config: 0x81,
+            threshold: [0x34, 0x12],
+            counter: u32::from_ne_bytes([1, 2, 3, 0xAA]),
+        }
+    );
+}
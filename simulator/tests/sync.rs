@@ -0,0 +1,99 @@
+use embedded_hal_i2c::block_on::BlockOnController;
+use embedded_hal_i2c::{
+    AnyAddress, SyncI2cController, SyncI2cTarget, SyncReadTransaction, SyncWriteTransaction,
+    Transaction,
+};
+use simulator::simulator;
+use std::time::Duration;
+
+const A7: u8 = 0x42;
+const ADDR: AnyAddress = AnyAddress::Seven(A7);
+
+#[test]
+fn write_read() {
+    let (mut c, mut t) = simulator();
+
+    let control = std::thread::spawn(move || {
+        let mut response = [0; 4];
+        c.write_read(A7, &[1, 2], &mut response).unwrap();
+        assert_eq!(response, [3, 4, 0xFF, 0xFF]);
+    });
+
+    let Transaction::Write { address, handler } = t.listen().unwrap() else {
+        panic!()
+    };
+    assert_eq!(address, ADDR);
+    let mut buf = [0; 2];
+    assert_eq!(handler.handle_complete(&mut buf).unwrap(), 2);
+    assert_eq!(buf, [1, 2]);
+
+    let Transaction::Read { address, handler } = t.listen().unwrap() else {
+        panic!()
+    };
+    assert_eq!(address, ADDR);
+    assert_eq!(handler.handle_complete(&[3, 4], 0xFF).unwrap(), 4);
+
+    assert!(matches!(t.listen().unwrap(), Transaction::Deselect));
+    control.join().unwrap();
+}
+
+#[test]
+fn listen_timeout() {
+    let (mut c, mut t) = simulator();
+
+    assert!(
+        t.listen_timeout(Duration::from_millis(10))
+            .unwrap()
+            .is_none()
+    );
+
+    let control = std::thread::spawn(move || {
+        c.write(A7, &[1]).unwrap();
+    });
+
+    let Some(Transaction::Write { address, handler }) =
+        t.listen_timeout(Duration::from_secs(1)).unwrap()
+    else {
+        panic!()
+    };
+    assert_eq!(address, ADDR);
+    let mut buf = [0];
+    assert_eq!(handler.handle_complete(&mut buf).unwrap(), 1);
+    assert_eq!(buf, [1]);
+
+    assert!(matches!(
+        t.listen_timeout(Duration::from_millis(10)).unwrap(),
+        Some(Transaction::Deselect)
+    ));
+    control.join().unwrap();
+
+    // The controller is gone, so there is nothing left to wait for.
+    assert!(t.listen_timeout(Duration::from_millis(10)).is_err());
+}
+
+#[test]
+fn block_on_controller() {
+    let (c, mut t) = simulator();
+
+    let control = std::thread::spawn(move || {
+        let mut c = BlockOnController::new(c);
+        let mut response = [0; 2];
+        c.write_read(A7, &[1], &mut response).unwrap();
+        assert_eq!(response, [2, 3]);
+    });
+
+    let Transaction::Write { handler, .. } = t.listen().unwrap() else {
+        panic!()
+    };
+    let mut buf = [0];
+    assert_eq!(handler.handle_complete(&mut buf).unwrap(), 1);
+    assert_eq!(buf, [1]);
+
+    let Transaction::Read { handler, .. } = t.listen().unwrap() else {
+        panic!()
+    };
+    assert_eq!(handler.handle_complete(&[2, 3], 0xFF).unwrap(), 2);
+
+    assert!(matches!(t.listen().unwrap(), Transaction::Deselect));
+    control.join().unwrap();
+}
@@ -0,0 +1,94 @@
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_i2c::target_ext::{AsyncI2cTargetExt, OperationProvider};
+use embedded_hal_i2c::{AnyAddress, AsyncI2cController, ErrorKind, NoAcknowledgeSource, Operation};
+use simulator::simulator;
+use std::time::Duration;
+
+const A7: u8 = 0x42;
+
+struct TokioDelay;
+
+impl DelayNs for TokioDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        tokio::time::sleep(Duration::from_nanos(ns.into())).await;
+    }
+}
+
+#[tokio::test]
+async fn drain() {
+    let (mut c, mut t) = simulator();
+    let mut other = c.clone();
+
+    // Keep the controllers around, so the target does not see them disconnect
+    let control = async {
+        let mut buf = [0; 2];
+        let (write, read) = tokio::join!(c.write(A7, &[1, 2]), other.read(A7, &mut buf));
+        let nak = Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        assert_eq!(write, nak);
+        assert_eq!(read, nak);
+    };
+
+    let target = async move {
+        assert_eq!(t.drain(&mut TokioDelay, 5_000).await, Ok(2));
+        // Nothing is left
+        assert_eq!(t.drain(&mut TokioDelay, 5_000).await, Ok(0));
+    };
+
+    tokio::join!(control, target);
+}
+
+/// Registers with an auto-incrementing pointer
+struct Registers {
+    data: [u8; 8],
+    pointer: usize,
+}
+
+impl OperationProvider for Registers {
+    fn write(&mut self, address: AnyAddress, data: &[u8]) {
+        assert_eq!(address, AnyAddress::Seven(A7));
+        self.pointer = usize::from(data[0]);
+    }
+
+    fn read(&mut self, _address: AnyAddress) -> &[u8] {
+        let start = self.pointer;
+        // Every read takes two bytes
+        self.pointer += 2;
+        &self.data[start..start + 2]
+    }
+}
+
+#[tokio::test]
+async fn handle_transaction() {
+    let (mut c, mut t) = simulator();
+
+    let control = async move {
+        let mut first = [0; 2];
+        let mut second = [0; 3];
+        c.transaction(
+            A7,
+            &mut [
+                Operation::Write(&[2]),
+                Operation::Read(&mut first),
+                Operation::Read(&mut second),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(first, [12, 13]);
+        assert_eq!(second, [14, 15, 0xFF]);
+    };
+
+    let target = async move {
+        let mut registers = Registers {
+            data: [10, 11, 12, 13, 14, 15, 16, 17],
+            pointer: 0,
+        };
+        let operations = t
+            .handle_transaction(&mut registers, &mut [0; 4], 0xFF)
+            .await
+            .unwrap();
+        assert_eq!(operations, 3);
+    };
+
+    tokio::join!(control, target);
+}